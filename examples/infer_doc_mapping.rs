@@ -0,0 +1,22 @@
+//! Prints the index configs inferred from the events emitted below. Replace them with calls
+//! into the code whose logs should be sent to Quickwit.
+
+use tracing_quickwit::DocMappingRecorder;
+use tracing_subscriber::layer::SubscriberExt;
+
+fn main() {
+    let recorder = DocMappingRecorder::new("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .map_marker_to_index("audit", "audit_logs");
+    let subscriber = tracing_subscriber::registry().with(recorder.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(task = "billing", customer = "acme", amount = 42_u64);
+        tracing::info!(task = "audit", user = "admin", action = "login");
+    });
+
+    for (index_id, index_config) in recorder.index_configs() {
+        let index_config = index_config.to_json(&index_id);
+        println!("{:#}", index_config);
+    }
+}
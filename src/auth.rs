@@ -0,0 +1,89 @@
+use reqwest::RequestBuilder;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// Headers and credentials attached to every request sent to Quickwit.
+#[derive(Default)]
+pub(crate) struct RequestCredentials {
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) authentication: Authentication,
+}
+
+#[derive(Default)]
+pub(crate) enum Authentication {
+    #[default]
+    None,
+    Bearer(String),
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    TokenProvider(TokenProvider),
+}
+
+pub(crate) struct TokenProvider {
+    provide: Box<dyn Fn() -> String + Send + Sync + 'static>,
+    refresh_interval: Duration,
+    cached: Mutex<Option<(String, Instant)>>,
+}
+
+impl RequestCredentials {
+    pub(crate) fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        for (name, value) in self.headers.iter() {
+            request = request.header(name, value);
+        }
+        match &self.authentication {
+            Authentication::None => request,
+            Authentication::Bearer(token) => request.bearer_auth(token),
+            Authentication::Basic { username, password } => {
+                request.basic_auth(username, password.as_ref())
+            }
+            Authentication::TokenProvider(provider) => request.bearer_auth(provider.token()),
+        }
+    }
+
+    // Makes the next request fetch a fresh token, e.g. after Quickwit answered `401`. Returns
+    // whether there's a token provider to fetch it from.
+    pub(crate) fn invalidate(&self) -> bool {
+        match &self.authentication {
+            Authentication::TokenProvider(provider) => {
+                *provider.lock() = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl TokenProvider {
+    pub(crate) fn new(
+        provide: impl Fn() -> String + Send + Sync + 'static,
+        refresh_interval: Duration,
+    ) -> Self {
+        Self {
+            provide: Box::new(provide),
+            refresh_interval,
+            cached: Mutex::new(None),
+        }
+    }
+
+    fn token(&self) -> String {
+        let mut cached = self.lock();
+        match cached.as_ref() {
+            Some((token, fetched_at)) if fetched_at.elapsed() < self.refresh_interval => {
+                token.clone()
+            }
+            _ => {
+                let token = (self.provide)();
+                *cached = Some((token.clone(), Instant::now()));
+                token
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<(String, Instant)>> {
+        self.cached
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,143 @@
+use crate::dropped::DropCounters;
+use crate::error::{ErrorHook, QuickwitError};
+use crate::message::QuickwitLogMessage;
+use crate::queue::{EventQueue, PushError};
+use crate::spill::SpillFile;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What happens to an event when the channel to the background task is full.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Drops the event being emitted.
+    #[default]
+    DropNewest,
+    /// Drops the oldest event waiting in the channel to make room for the one being emitted.
+    DropOldest,
+    /// Blocks the emitting thread until there's room or `timeout` elapses, then drops the event.
+    ///
+    /// Mind that on a current-thread runtime the background task can't make room while the only
+    /// thread is blocked.
+    Block { timeout: Duration },
+    /// Appends the event to an NDJSON file the background task sends once it catches up.
+    SpillToDisk { path: PathBuf },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(usize)]
+pub enum DropReason {
+    /// The channel was full and the event being emitted was dropped.
+    ChannelFull,
+    /// The event was evicted from the channel by a newer one.
+    EvictedByNewer,
+    /// The channel stayed full for the whole blocking timeout.
+    BlockTimedOut,
+    /// The event couldn't be written to the spill file.
+    SpillFailed,
+}
+
+impl DropReason {
+    pub const ALL: [DropReason; 4] = [
+        DropReason::ChannelFull,
+        DropReason::EvictedByNewer,
+        DropReason::BlockTimedOut,
+        DropReason::SpillFailed,
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedEvent {
+    pub index_id: String,
+    pub reason: DropReason,
+    /// How many events were dropped since the layer was built, including this one.
+    pub total_dropped: u64,
+}
+
+pub(crate) type DroppedEventHook = Arc<dyn Fn(&DroppedEvent) + Send + Sync + 'static>;
+
+// The sending half of the channel between the layer and the background task.
+pub(crate) struct EventSender {
+    queue: Arc<EventQueue>,
+    policy: BackpressurePolicy,
+    spill_file: Option<Arc<SpillFile>>,
+    on_event_dropped: DroppedEventHook,
+    on_error: ErrorHook,
+    drop_counters: Arc<DropCounters>,
+}
+
+impl EventSender {
+    pub(crate) fn new(
+        queue: Arc<EventQueue>,
+        policy: BackpressurePolicy,
+        spill_file: Option<Arc<SpillFile>>,
+        on_event_dropped: DroppedEventHook,
+        on_error: ErrorHook,
+        drop_counters: Arc<DropCounters>,
+    ) -> Self {
+        Self {
+            queue,
+            policy,
+            spill_file,
+            on_event_dropped,
+            on_error,
+            drop_counters,
+        }
+    }
+
+    pub(crate) fn send(&self, message: QuickwitLogMessage) {
+        let dropped = match &self.policy {
+            BackpressurePolicy::DropNewest => match self.queue.try_push(message) {
+                Err(PushError::Full(message)) => Some((message, DropReason::ChannelFull)),
+                Ok(()) | Err(PushError::Closed) => None,
+            },
+            BackpressurePolicy::DropOldest => match self.queue.push_evicting_oldest(message) {
+                Ok(Some(evicted)) => Some((evicted, DropReason::EvictedByNewer)),
+                Ok(None) | Err(_) => None,
+            },
+            BackpressurePolicy::Block { timeout } => {
+                match self.queue.push_blocking(message, *timeout) {
+                    Err(PushError::Full(message)) => Some((message, DropReason::BlockTimedOut)),
+                    Ok(()) | Err(PushError::Closed) => None,
+                }
+            }
+            BackpressurePolicy::SpillToDisk { .. } => match self.queue.try_push(message) {
+                Err(PushError::Full(message)) => self.spill(message),
+                Ok(()) | Err(PushError::Closed) => None,
+            },
+        };
+        if let Some((message, reason)) = dropped {
+            self.report_dropped(message.index_id, reason);
+        }
+    }
+
+    pub(crate) fn close(&self) {
+        self.queue.close();
+    }
+
+    fn spill(&self, message: QuickwitLogMessage) -> Option<(QuickwitLogMessage, DropReason)> {
+        let spill_file = self.spill_file.as_ref()?;
+        match spill_file.append(&message) {
+            Ok(()) => None,
+            Err(err) => {
+                (self.on_error)(QuickwitError::Spill { source: err });
+                Some((message, DropReason::SpillFailed))
+            }
+        }
+    }
+
+    fn report_dropped(&self, index_id: String, reason: DropReason) {
+        let total_dropped = self.drop_counters.record(&index_id, reason);
+        // Failing to spill is reported as an error of its own.
+        if reason != DropReason::SpillFailed {
+            (self.on_error)(QuickwitError::ChannelFull {
+                index_id: index_id.clone(),
+            });
+        }
+        (self.on_event_dropped)(&DroppedEvent {
+            index_id,
+            reason,
+            total_dropped,
+        });
+    }
+}
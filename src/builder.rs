@@ -1,15 +1,37 @@
-use crate::defaults::DEFAULT_LOGGING_BUFFER_SIZE;
+use crate::auth::{Authentication, RequestCredentials, TokenProvider};
+use crate::backpressure::{BackpressurePolicy, DroppedEvent, DroppedEventHook, EventSender};
+use crate::commit::CommitMode;
+use crate::compression::Compression;
+use crate::defaults::{DEFAULT_CHANNEL_CAPACITY, DEFAULT_LOGGING_BUFFER_SIZE};
+use crate::document::DocumentConfig;
+use crate::dropped::DropCounters;
+use crate::endpoint::normalize_base_url;
+use crate::error::{ErrorHook, QuickwitError};
+use crate::handle::QuickwitHandle;
+use crate::http::{CaBundle, HttpClientConfig};
+use crate::index_config::IndexConfig;
 use crate::layer::QuickwitLoggingLayer;
-use crate::message::QuickwitLogMessage;
-use crate::ndjson;
-use reqwest::Client;
+use crate::metadata::{MetadataField, MetadataLayout};
+use crate::protocol::IngestProtocol;
+use crate::queue::EventQueue;
+use crate::report::IngestReport;
+use crate::retry::RetryPolicy;
+use crate::span::SpanLayout;
+use crate::spill::SpillFile;
+use crate::timestamp::TimestampFormat;
+use crate::trace::TraceExport;
+use crate::validation::{is_valid_index_id, ConfigError, ConfigProblem};
+use crate::worker::{Worker, WorkerConfig};
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Client, Identity};
 use std::collections::HashMap;
 use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use url::Url;
 
-#[cfg(feature = "testing-extras")]
-use std::sync::Arc;
 #[cfg(feature = "testing-extras")]
 use tokio::sync::Notify;
 
@@ -19,8 +41,22 @@ pub struct QuickwitLoggingLayerBuilder {
     // TODO: Consider `&'static str`.
     field_to_index: HashMap<String, String>,
     batch_size: usize,
-    on_index_missing: Box<dyn Fn() + Send + Sync + 'static>,
-    on_ingest_failed: Box<dyn Fn(reqwest::Error) + Send + Sync + 'static>,
+    flush_interval: Option<Duration>,
+    document_config: DocumentConfig,
+    retry_policy: RetryPolicy,
+    compression: Compression,
+    ingest_protocol: IngestProtocol,
+    commit_mode: CommitMode,
+    index_commit_modes: HashMap<String, CommitMode>,
+    index_configs: HashMap<String, IndexConfig>,
+    credentials: RequestCredentials,
+    http_client: HttpClientConfig,
+    channel_capacity: usize,
+    backpressure_policy: BackpressurePolicy,
+    dropped_events_summary: bool,
+    on_error: ErrorHook,
+    on_event_dropped: DroppedEventHook,
+    on_ingest_report: Arc<dyn Fn(&IngestReport) + Send + Sync + 'static>,
     #[cfg(feature = "testing-extras")]
     expected_emitted_events_count: usize,
     #[cfg(feature = "testing-extras")]
@@ -28,15 +64,30 @@ pub struct QuickwitLoggingLayerBuilder {
 }
 
 impl QuickwitLoggingLayerBuilder {
-    // TODO: Check that `quickwit_url` is reachable and warn if it isn't.
+    /// `quickwit_url` may include a path prefix, e.g. `https://gw.example.com/quickwit`. Whether
+    /// it's reachable can be checked with `QuickwitHandle::verify`.
     pub fn new(quickwit_url: impl Into<Url>) -> Self {
         Self {
             quickwit_url: quickwit_url.into(),
             target_field: String::new(),
             field_to_index: HashMap::new(),
             batch_size: DEFAULT_LOGGING_BUFFER_SIZE,
-            on_index_missing: Box::new(|| ()),
-            on_ingest_failed: Box::new(|_err| ()),
+            flush_interval: None,
+            document_config: DocumentConfig::default(),
+            retry_policy: RetryPolicy::default(),
+            compression: Compression::default(),
+            ingest_protocol: IngestProtocol::default(),
+            commit_mode: CommitMode::default(),
+            index_commit_modes: HashMap::new(),
+            index_configs: HashMap::new(),
+            credentials: RequestCredentials::default(),
+            http_client: HttpClientConfig::default(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            backpressure_policy: BackpressurePolicy::default(),
+            dropped_events_summary: false,
+            on_error: Arc::new(|_err| ()),
+            on_event_dropped: Arc::new(|_dropped| ()),
+            on_ingest_report: Arc::new(|_report| ()),
             #[cfg(feature = "testing-extras")]
             expected_emitted_events_count: 0,
             #[cfg(feature = "testing-extras")]
@@ -60,16 +111,214 @@ impl QuickwitLoggingLayerBuilder {
         self
     }
 
-    pub fn on_index_missing(mut self, callback: impl Fn() + Send + Sync + 'static) -> Self {
-        self.on_index_missing = Box::new(callback);
+    /// Flushes a partially filled batch once its oldest log has waited for `flush_interval`.
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = Some(flush_interval);
+        self
+    }
+
+    /// Name of the field holding the time the event was recorded at, `timestamp` by default.
+    pub fn with_timestamp_field(mut self, field: impl Into<String>) -> Self {
+        self.document_config.timestamp_field = Some(field.into());
+        self
+    }
+
+    pub fn with_timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.document_config.timestamp_format = format;
+        self
+    }
+
+    /// Sends the fields of events as they are, without adding a timestamp.
+    pub fn without_timestamp(mut self) -> Self {
+        self.document_config.timestamp_field = None;
+        self
+    }
+
+    /// Adds `field` of the event's metadata to its document under `MetadataField::default_key`.
+    pub fn with_metadata_field(self, field: MetadataField) -> Self {
+        self.with_metadata_field_as(field, field.default_key())
+    }
+
+    pub fn with_metadata_field_as(mut self, field: MetadataField, key: impl Into<String>) -> Self {
+        self.document_config.add_metadata_field(field, key.into());
+        self
+    }
+
+    pub fn with_metadata_layout(mut self, layout: MetadataLayout) -> Self {
+        self.document_config.metadata_layout = layout;
+        self
+    }
+
+    pub fn with_span_layout(mut self, layout: SpanLayout) -> Self {
+        self.document_config.span_layout = layout;
         self
     }
 
-    pub fn on_ingest_failed(
+    /// Exports spans as traces in addition to events. Spans are sent to the traces index the same
+    /// way events are sent to the mapped indexes, sharing their batching, retries and backpressure.
+    pub fn with_trace_export(mut self, trace_export: TraceExport) -> Self {
+        self.document_config.trace_export = Some(trace_export);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_ingest_protocol(mut self, ingest_protocol: IngestProtocol) -> Self {
+        self.ingest_protocol = ingest_protocol;
+        self
+    }
+
+    /// Commit mode of the indexes that don't have one set with `with_index_commit_mode`.
+    pub fn with_commit_mode(mut self, commit_mode: CommitMode) -> Self {
+        self.commit_mode = commit_mode;
+        self
+    }
+
+    pub fn with_index_commit_mode(
         mut self,
-        callback: impl Fn(reqwest::Error) + Send + Sync + 'static,
+        index_id: impl Into<String>,
+        commit_mode: CommitMode,
     ) -> Self {
-        self.on_ingest_failed = Box::new(callback);
+        self.index_commit_modes.insert(index_id.into(), commit_mode);
+        self
+    }
+
+    /// Makes the background task create `index_id` from `index_config` before the first flush
+    /// to it if it doesn't exist. See also `QuickwitHandle::create_missing_indexes`.
+    pub fn with_index_config(
+        mut self,
+        index_id: impl Into<String>,
+        index_config: IndexConfig,
+    ) -> Self {
+        self.index_configs.insert(index_id.into(), index_config);
+        self
+    }
+
+    /// Adds a header to every request sent to Quickwit.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.credentials.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.credentials.authentication = Authentication::Bearer(token.into());
+        self
+    }
+
+    pub fn with_basic_auth(
+        mut self,
+        username: impl Into<String>,
+        password: Option<impl Into<String>>,
+    ) -> Self {
+        self.credentials.authentication = Authentication::Basic {
+            username: username.into(),
+            password: password.map(Into::into),
+        };
+        self
+    }
+
+    /// Sends the token returned by `provider` as a bearer token. The provider is called again
+    /// once `refresh_interval` has passed since the last call or after Quickwit answered `401`,
+    /// in which case the request is resent right away with the fresh token.
+    ///
+    /// The provider runs on the background task, so it must not block, e.g. on network I/O.
+    /// Fetch tokens elsewhere instead, like on a thread of your own, and return the latest one.
+    pub fn with_token_provider(
+        mut self,
+        provider: impl Fn() -> String + Send + Sync + 'static,
+        refresh_interval: Duration,
+    ) -> Self {
+        self.credentials.authentication =
+            Authentication::TokenProvider(TokenProvider::new(provider, refresh_interval));
+        self
+    }
+
+    /// Sends requests with `client` instead of a client built from the connect timeout, CA
+    /// bundle and client identity options, which are ignored then. `try_build` reports them as
+    /// `ConfigProblem::OverriddenByHttpClient`.
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.http_client.client = Some(client);
+        self
+    }
+
+    /// Applies to every request, including the ones sent by a custom client.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.http_client.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http_client.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Trusts the PEM-encoded root certificates in `pem_bundle` in addition to the system ones.
+    pub fn with_ca_bundle(mut self, pem_bundle: impl Into<Vec<u8>>) -> Self {
+        self.http_client.ca_bundle = Some(CaBundle::Pem(pem_bundle.into()));
+        self
+    }
+
+    /// Like `with_ca_bundle`, with the bundle read from the file at `path` when building.
+    pub fn with_ca_bundle_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.http_client.ca_bundle = Some(CaBundle::File(path.into()));
+        self
+    }
+
+    /// Presents `identity` to Quickwit (or the proxy in front of it) for mutual TLS.
+    pub fn with_client_identity(mut self, identity: Identity) -> Self {
+        self.http_client.identity = Some(identity);
+        self
+    }
+
+    /// How many events can wait for the background task before `BackpressurePolicy` kicks in.
+    pub fn with_channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity;
+        self
+    }
+
+    pub fn with_backpressure_policy(mut self, backpressure_policy: BackpressurePolicy) -> Self {
+        self.backpressure_policy = backpressure_policy;
+        self
+    }
+
+    /// Makes the background task add a document like "N events dropped between t1 and t2" to an
+    /// index on the next successful flush after events headed to it were dropped.
+    pub fn with_dropped_events_summary(mut self, enabled: bool) -> Self {
+        self.dropped_events_summary = enabled;
+        self
+    }
+
+    /// Called with every error that happens either while routing events or in the background
+    /// task. Mind that it's called from within `on_event`, so emitting events from it with the
+    /// same subscriber may recurse.
+    pub fn on_error(mut self, callback: impl Fn(QuickwitError) + Send + Sync + 'static) -> Self {
+        self.on_error = Arc::new(callback);
+        self
+    }
+
+    pub fn on_event_dropped(
+        mut self,
+        callback: impl Fn(&DroppedEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_event_dropped = Arc::new(callback);
+        self
+    }
+
+    /// Called with what Quickwit reported about every batch it accepted, e.g. to detect documents
+    /// rejected because of a doc mapping mismatch.
+    pub fn on_ingest_report(
+        mut self,
+        callback: impl Fn(&IngestReport) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_ingest_report = Arc::new(callback);
         self
     }
 
@@ -85,64 +334,184 @@ impl QuickwitLoggingLayerBuilder {
         self
     }
 
-    pub fn build(self) -> (QuickwitLoggingLayer, impl Future<Output = impl Send> + Send) {
-        let http_client = Client::new();
-        // TODO: Capacity should be configurable.
-        let (sender, mut receiver) = mpsc::channel(500);
-        let index_names = self.field_to_index.values().cloned().collect::<Vec<_>>();
-        let background_task = async move {
-            let mut buffers = HashMap::new();
-            for index_name in index_names {
-                buffers.insert(index_name, Vec::with_capacity(self.batch_size));
-            }
-            while let Some(QuickwitLogMessage { index_id, log }) = receiver.recv().await {
-                let buffer = match buffers.get_mut(&index_id) {
-                    Some(buffer) => buffer,
-                    None => buffers
-                        .entry(index_id.clone())
-                        .or_insert(Vec::with_capacity(self.batch_size)),
-                };
-                buffer.push(log);
-                if buffer.len() >= self.batch_size {
-                    // TODO: Reuse `ndjson_body`.
-                    let mut ndjson_body = Vec::new();
-                    for log in buffer.iter() {
-                        ndjson::serialize(&mut ndjson_body, log).unwrap();
-                    }
-                    let response = http_client
-                        .post(format!("{}api/v1/{}/ingest", self.quickwit_url, index_id))
-                        .body(ndjson_body)
-                        .send()
-                        .await;
-                    if let Err(err) = response {
-                        (self.on_ingest_failed)(err);
-                    }
-                    buffer.clear();
-                }
+    /// Builds the layer even if `try_build` would report problems that don't prevent it from
+    /// running, like an empty marker field or no index being mapped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Quickwit URL can't have paths joined to it, the HTTP client can't be built,
+    /// a header can't be sent or the channel capacity is zero.
+    pub fn build(
+        self,
+    ) -> (
+        QuickwitLoggingLayer,
+        QuickwitHandle,
+        impl Future<Output = impl Send> + Send,
+    ) {
+        match self.build_checked(ConfigProblem::prevents_building) {
+            Ok(built) => built,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Like `build`, but fails on every configuration problem instead of panicking on the ones
+    /// that prevent the layer from running, and reports all of them.
+    pub fn try_build(
+        self,
+    ) -> Result<
+        (
+            QuickwitLoggingLayer,
+            QuickwitHandle,
+            impl Future<Output = impl Send> + Send,
+        ),
+        ConfigError,
+    > {
+        self.build_checked(|_| true)
+    }
+
+    // Fails if any of the problems found is `rejected`.
+    fn build_checked(
+        self,
+        rejected: impl Fn(&ConfigProblem) -> bool,
+    ) -> Result<
+        (
+            QuickwitLoggingLayer,
+            QuickwitHandle,
+            impl Future<Output = impl Send> + Send,
+        ),
+        ConfigError,
+    > {
+        let mut problems = Vec::new();
+        if self.target_field.is_empty() {
+            problems.push(ConfigProblem::EmptyMarkerField);
+        }
+        if self.field_to_index.is_empty() {
+            problems.push(ConfigProblem::NoIndexMapped);
+        }
+        if self.batch_size == 0 {
+            problems.push(ConfigProblem::ZeroBatchSize);
+        }
+        if self.channel_capacity == 0 {
+            problems.push(ConfigProblem::ZeroChannelCapacity);
+        }
+        let scheme = self.quickwit_url.scheme();
+        if scheme != "http" && scheme != "https" {
+            problems.push(ConfigProblem::UnsupportedScheme {
+                scheme: scheme.to_string(),
+            });
+        }
+        let quickwit_url = normalize_base_url(self.quickwit_url)
+            .map_err(|problem| problems.push(problem))
+            .ok();
+        let mut index_ids = self
+            .field_to_index
+            .values()
+            .chain(self.index_commit_modes.keys())
+            .chain(self.index_configs.keys())
+            .chain(
+                self.document_config
+                    .trace_export
+                    .as_ref()
+                    .map(|trace_export| &trace_export.index_id),
+            )
+            .filter(|index_id| !is_valid_index_id(index_id))
+            .collect::<Vec<_>>();
+        index_ids.sort();
+        index_ids.dedup();
+        for index_id in index_ids {
+            problems.push(ConfigProblem::InvalidIndexId {
+                index_id: index_id.clone(),
+            });
+        }
+        for (name, value) in self.credentials.headers.iter() {
+            if HeaderName::from_bytes(name.as_bytes()).is_err()
+                || HeaderValue::from_str(value).is_err()
+            {
+                problems.push(ConfigProblem::InvalidHeader { name: name.clone() });
             }
-            for (index_id, buffer) in buffers.iter_mut() {
-                let mut ndjson_body = Vec::new();
-                for log in buffer.iter() {
-                    ndjson::serialize(&mut ndjson_body, log).unwrap();
-                }
-                let _response = http_client
-                    .post(format!("{}api/v1/{}/ingest", self.quickwit_url, index_id))
-                    .body(ndjson_body)
-                    .send()
-                    .await;
-                buffer.clear();
+        }
+        let overridden_options = self.http_client.overridden_options();
+        if !overridden_options.is_empty() {
+            problems.push(ConfigProblem::OverriddenByHttpClient {
+                options: overridden_options,
+            });
+        }
+        let request_timeout = self.http_client.request_timeout;
+        let http_client = self
+            .http_client
+            .build_client()
+            .map_err(|err| problems.push(ConfigProblem::HttpClient { source: err }))
+            .ok();
+        let (Some(quickwit_url), Some(http_client), false) =
+            (quickwit_url, http_client, problems.iter().any(&rejected))
+        else {
+            return Err(ConfigError { problems });
+        };
+        let queue = Arc::new(EventQueue::new(self.channel_capacity));
+        let traces_index_id = self
+            .document_config
+            .trace_export
+            .as_ref()
+            .map(|trace_export| trace_export.index_id.clone());
+        let index_ids = self
+            .field_to_index
+            .values()
+            .cloned()
+            .chain(traces_index_id)
+            .collect::<Vec<_>>();
+        let drop_counters = Arc::new(DropCounters::new(index_ids.iter().cloned()));
+        let spill_file = match &self.backpressure_policy {
+            BackpressurePolicy::SpillToDisk { path } => {
+                Some(Arc::new(SpillFile::new(path.clone())))
             }
+            _ => None,
+        };
+        let (command_sender, command_receiver) = mpsc::unbounded_channel();
+        let worker_config = WorkerConfig {
+            http_client,
+            request_timeout,
+            quickwit_url,
+            batch_size: self.batch_size,
+            flush_interval: self.flush_interval,
+            retry_policy: self.retry_policy,
+            compression: self.compression,
+            ingest_protocol: self.ingest_protocol,
+            commit_mode: self.commit_mode,
+            index_commit_modes: self.index_commit_modes,
+            index_configs: self.index_configs,
+            credentials: self.credentials,
+            on_error: Arc::clone(&self.on_error),
+            spill_file: spill_file.clone(),
+            drop_counters: Arc::clone(&drop_counters),
+            dropped_events_summary: self.dropped_events_summary,
+            document_config: self.document_config.clone(),
+            on_ingest_report: self.on_ingest_report,
         };
+        let worker = Worker::new(worker_config, index_ids);
+        let background_task = worker.run(Arc::clone(&queue), command_receiver);
+        let sender = EventSender::new(
+            queue,
+            self.backpressure_policy,
+            spill_file,
+            self.on_event_dropped,
+            Arc::clone(&self.on_error),
+            Arc::clone(&drop_counters),
+        );
         let layer = QuickwitLoggingLayer::new(
             sender,
             self.target_field,
             self.field_to_index,
-            self.on_index_missing,
+            self.document_config,
+            self.on_error,
             #[cfg(feature = "testing-extras")]
             self.expected_emitted_events_count,
             #[cfg(feature = "testing-extras")]
             self.emitted_all,
         );
-        (layer, background_task)
+        Ok((
+            layer,
+            QuickwitHandle::new(command_sender, drop_counters),
+            background_task,
+        ))
     }
 }
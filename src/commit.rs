@@ -0,0 +1,30 @@
+/// When documents sent to Quickwit become searchable, see the `commit` parameter of the ingest API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CommitMode {
+    /// Quickwit commits on its own schedule and answers right away.
+    #[default]
+    Auto,
+    /// Quickwit answers once the documents are committed on its own schedule.
+    WaitFor,
+    /// Quickwit commits right away and answers once the documents are committed.
+    Force,
+}
+
+impl CommitMode {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            CommitMode::Auto => "auto",
+            CommitMode::WaitFor => "wait_for",
+            CommitMode::Force => "force",
+        }
+    }
+
+    // The `refresh` parameter of the Elasticsearch-compatible bulk API.
+    pub(crate) fn as_elastic_refresh(self) -> Option<&'static str> {
+        match self {
+            CommitMode::Auto => None,
+            CommitMode::WaitFor => Some("wait_for"),
+            CommitMode::Force => Some("true"),
+        }
+    }
+}
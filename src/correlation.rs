@@ -0,0 +1,44 @@
+use crate::trace::SpanRecord;
+use opentelemetry::trace::TraceContextExt;
+use tracing_core::Subscriber;
+use tracing_opentelemetry::OtelData;
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+
+const TRACE_ID_FIELD: &str = "trace_id";
+const SPAN_ID_FIELD: &str = "span_id";
+
+// Adds the ids of the span an event happened in to its document, preferring the OpenTelemetry
+// context of `tracing-opentelemetry` over the ids generated for `TraceExport`.
+pub(crate) fn insert_trace_context<S>(
+    document: &mut serde_json::Map<String, serde_json::Value>,
+    span: &SpanRef<'_, S>,
+) where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let extensions = span.extensions();
+    let ids = extensions.get::<OtelData>().and_then(otel_ids).or_else(|| {
+        let span_record = extensions.get::<SpanRecord>()?;
+        Some((span_record.trace_id.clone(), span_record.span_id.clone()))
+    });
+    if let Some((trace_id, span_id)) = ids {
+        document
+            .entry(TRACE_ID_FIELD)
+            .or_insert_with(|| trace_id.into());
+        document
+            .entry(SPAN_ID_FIELD)
+            .or_insert_with(|| span_id.into());
+    }
+}
+
+fn otel_ids(otel_data: &OtelData) -> Option<(String, String)> {
+    let span_id = otel_data.builder.span_id?;
+    // Only root spans have their trace id in the builder, the others inherit the parent's one.
+    let trace_id = otel_data.builder.trace_id.or_else(|| {
+        let parent_span = otel_data.parent_cx.span();
+        let parent_span_context = parent_span.span_context();
+        parent_span_context
+            .is_valid()
+            .then(|| parent_span_context.trace_id())
+    })?;
+    Some((trace_id.to_string(), span_id.to_string()))
+}
@@ -1 +1,9 @@
+use std::time::Duration;
+
 pub(crate) const DEFAULT_LOGGING_BUFFER_SIZE: usize = 500;
+pub(crate) const DEFAULT_CHANNEL_CAPACITY: usize = 500;
+pub(crate) const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 3;
+pub(crate) const DEFAULT_RETRY_BASE_BACKOFF: Duration = Duration::from_millis(100);
+pub(crate) const DEFAULT_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(10);
+pub(crate) const DEFAULT_TIMESTAMP_FIELD: &str = "timestamp";
+pub(crate) const DEFAULT_TRACES_INDEX_ID: &str = "otel-traces-v0_7";
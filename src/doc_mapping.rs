@@ -0,0 +1,355 @@
+#[cfg(feature = "opentelemetry")]
+use crate::correlation;
+use crate::document::DocumentConfig;
+use crate::index_config::{FieldMapping, FieldType, IndexConfig};
+use crate::metadata::{MetadataField, MetadataLayout};
+use crate::span::{self, SpanLayout};
+use crate::timestamp::TimestampFormat;
+use crate::visitor::TargetFieldVisitor;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing_core::callsite::Identifier;
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::subscriber::Interest;
+use tracing_core::{Event, Metadata, Subscriber};
+use tracing_subscriber::layer::Context as TracingContext;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// A layer inferring the doc mappings of the indexes events are routed to, e.g. to write their
+/// `IndexConfig` from a test exercising the code that logs.
+///
+/// It routes events the way `QuickwitLoggingLayer` does and maps every field to the type of the
+/// values recorded for it, e.g. `bool` for `done = true`. The fields the layer adds to documents
+/// are mapped too, so the metadata fields, span layout and timestamp have to be configured the way
+/// they are on `QuickwitLoggingLayerBuilder`. Spans and metadata nested under a key are mapped as
+/// a JSON field, and the timestamp field is made the indexes' timestamp field. Fields declared at
+/// a call site but never recorded are mapped as text. Clones share what was recorded.
+#[derive(Clone)]
+pub struct DocMappingRecorder {
+    target_field: String,
+    field_to_index: HashMap<String, String>,
+    document_config: DocumentConfig,
+    state: Arc<Mutex<RecorderState>>,
+}
+
+#[derive(Default)]
+struct RecorderState {
+    // Fields of the event call sites, whose events may be routed by the marker of a span.
+    callsite_fields: HashMap<Identifier, Vec<&'static str>>,
+    callsite_indexes: HashMap<Identifier, HashSet<String>>,
+    observed_types: HashMap<String, BTreeMap<String, FieldType>>,
+}
+
+// Stored in the extensions of every span while spans aren't omitted.
+struct SpanFieldTypes(BTreeMap<String, FieldType>);
+
+impl DocMappingRecorder {
+    pub fn new(marker_field: impl Into<String>) -> Self {
+        Self {
+            target_field: marker_field.into(),
+            field_to_index: HashMap::new(),
+            document_config: DocumentConfig::default(),
+            state: Arc::new(Mutex::new(RecorderState::default())),
+        }
+    }
+
+    pub fn map_marker_to_index<S: Into<String>>(mut self, field_value: S, index_id: S) -> Self {
+        self.field_to_index
+            .insert(field_value.into(), index_id.into());
+        self
+    }
+
+    pub fn with_timestamp_field(mut self, field: impl Into<String>) -> Self {
+        self.document_config.timestamp_field = Some(field.into());
+        self
+    }
+
+    pub fn with_timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.document_config.timestamp_format = format;
+        self
+    }
+
+    pub fn without_timestamp(mut self) -> Self {
+        self.document_config.timestamp_field = None;
+        self
+    }
+
+    pub fn with_metadata_field(self, field: MetadataField) -> Self {
+        self.with_metadata_field_as(field, field.default_key())
+    }
+
+    pub fn with_metadata_field_as(mut self, field: MetadataField, key: impl Into<String>) -> Self {
+        self.document_config.add_metadata_field(field, key.into());
+        self
+    }
+
+    pub fn with_metadata_layout(mut self, layout: MetadataLayout) -> Self {
+        self.document_config.metadata_layout = layout;
+        self
+    }
+
+    pub fn with_span_layout(mut self, span_layout: SpanLayout) -> Self {
+        self.document_config.span_layout = span_layout;
+        self
+    }
+
+    /// Index configs of every mapped index, with a field mapping per recorded field. Since they
+    /// serialize to JSON, which is valid YAML, they can be passed to `quickwit index create` as
+    /// well.
+    pub fn index_configs(&self) -> HashMap<String, IndexConfig> {
+        let state = self.lock();
+        let mut index_fields = HashMap::<&str, BTreeMap<String, FieldType>>::new();
+        for index_id in self.field_to_index.values() {
+            index_fields.entry(index_id).or_default();
+        }
+        for (callsite, index_ids) in state.callsite_indexes.iter() {
+            let fields = state
+                .callsite_fields
+                .get(callsite)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for index_id in index_ids {
+                let index_fields = index_fields.entry(index_id).or_default();
+                for field in fields {
+                    index_fields
+                        .entry(field.to_string())
+                        .or_insert(FieldType::Text);
+                }
+            }
+        }
+        for (index_id, observed_types) in state.observed_types.iter() {
+            index_fields
+                .entry(index_id)
+                .or_default()
+                .extend(observed_types.clone());
+        }
+        index_fields
+            .into_iter()
+            .map(|(index_id, fields)| {
+                let mut index_config = fields.into_iter().fold(
+                    IndexConfig::new(),
+                    |index_config, (name, field_type)| {
+                        index_config.with_field_mapping(FieldMapping::new(name, field_type))
+                    },
+                );
+                if let Some(timestamp_field) = &self.document_config.timestamp_field {
+                    index_config = index_config
+                        .with_timestamp_field(timestamp_field)
+                        .with_timestamp_format(self.document_config.timestamp_format);
+                }
+                (index_id.to_string(), index_config)
+            })
+            .collect()
+    }
+
+    // The types of the fields the layer adds to the fields of an event.
+    fn added_field_types<S>(
+        &self,
+        event: &Event<'_>,
+        ctx: &TracingContext<'_, S>,
+    ) -> BTreeMap<String, FieldType>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let mut types = BTreeMap::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            match &self.document_config.span_layout {
+                SpanLayout::Flattened => {
+                    for span in scope {
+                        if let Some(SpanFieldTypes(span_types)) = span.extensions().get() {
+                            for (field, field_type) in span_types {
+                                types.entry(field.clone()).or_insert(*field_type);
+                            }
+                        }
+                    }
+                }
+                SpanLayout::Nested(key) => {
+                    types.insert(key.clone(), FieldType::Json);
+                }
+                SpanLayout::Omitted => {}
+            }
+        }
+        let metadata = event.metadata();
+        let metadata_types = self
+            .document_config
+            .metadata_fields
+            .iter()
+            .filter(|(field, _)| field.value(metadata).is_some())
+            .map(|(field, key)| (key, metadata_field_type(*field)));
+        match &self.document_config.metadata_layout {
+            MetadataLayout::Flattened => {
+                for (key, field_type) in metadata_types {
+                    types.entry(key.clone()).or_insert(field_type);
+                }
+            }
+            MetadataLayout::Nested(key) => {
+                if metadata_types.count() > 0 {
+                    types.entry(key.clone()).or_insert(FieldType::Json);
+                }
+            }
+        }
+        #[cfg(feature = "opentelemetry")]
+        if let Some(span) = ctx.event_span(event) {
+            let mut trace_context = serde_json::Map::new();
+            correlation::insert_trace_context(&mut trace_context, &span);
+            for key in trace_context.keys() {
+                types.entry(key.clone()).or_insert(FieldType::Text);
+            }
+        }
+        types
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RecorderState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<S> Layer<S> for DocMappingRecorder
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if metadata.is_event() {
+            let fields = metadata.fields().iter().map(|field| field.name()).collect();
+            self.lock()
+                .callsite_fields
+                .insert(metadata.callsite(), fields);
+        }
+        Interest::always()
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: TracingContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut target_field_visitor = TargetFieldVisitor::new(&self.target_field);
+        attrs.record(&mut target_field_visitor);
+        if let Some(target_value) = target_field_visitor.target_value {
+            span::record_marker(&span, &self.target_field, target_value);
+        }
+        if self.document_config.span_layout == SpanLayout::Omitted {
+            return;
+        }
+        let mut visitor = FieldTypeVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().replace(SpanFieldTypes(visitor.types));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: TracingContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut target_field_visitor = TargetFieldVisitor::new(&self.target_field);
+        values.record(&mut target_field_visitor);
+        if let Some(target_value) = target_field_visitor.target_value {
+            span::record_marker(&span, &self.target_field, target_value);
+        }
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanFieldTypes(span_types)) = extensions.get_mut() {
+            let mut visitor = FieldTypeVisitor::default();
+            values.record(&mut visitor);
+            observe(span_types, visitor.types);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: TracingContext<'_, S>) {
+        let mut target_field_visitor = TargetFieldVisitor::new(&self.target_field);
+        event.record(&mut target_field_visitor);
+        let Some(index_id) = target_field_visitor
+            .target_value
+            .or_else(|| span::inherited_marker(ctx.event_scope(event)?, &self.target_field))
+            .and_then(|target_value| self.field_to_index.get(&target_value))
+        else {
+            return;
+        };
+        let mut visitor = FieldTypeVisitor::default();
+        event.record(&mut visitor);
+        // The fields of the event win over the ones the layer adds.
+        for (field, field_type) in self.added_field_types(event, &ctx) {
+            visitor.types.entry(field).or_insert(field_type);
+        }
+        let mut state = self.lock();
+        state
+            .callsite_indexes
+            .entry(event.metadata().callsite())
+            .or_default()
+            .insert(index_id.clone());
+        let observed_types = state.observed_types.entry(index_id.clone()).or_default();
+        observe(observed_types, visitor.types);
+    }
+}
+
+// Records the kind of every value instead of the value.
+#[derive(Default)]
+struct FieldTypeVisitor {
+    types: BTreeMap<String, FieldType>,
+}
+
+impl FieldTypeVisitor {
+    fn insert(&mut self, field: &Field, field_type: FieldType) {
+        self.types.insert(field.name().to_string(), field_type);
+    }
+}
+
+impl Visit for FieldTypeVisitor {
+    fn record_f64(&mut self, field: &Field, _value: f64) {
+        self.insert(field, FieldType::F64);
+    }
+
+    fn record_i64(&mut self, field: &Field, _value: i64) {
+        self.insert(field, FieldType::I64);
+    }
+
+    fn record_u64(&mut self, field: &Field, _value: u64) {
+        self.insert(field, FieldType::U64);
+    }
+
+    fn record_i128(&mut self, field: &Field, _value: i128) {
+        self.insert(field, FieldType::I64);
+    }
+
+    fn record_u128(&mut self, field: &Field, _value: u128) {
+        self.insert(field, FieldType::U64);
+    }
+
+    fn record_bool(&mut self, field: &Field, _value: bool) {
+        self.insert(field, FieldType::Bool);
+    }
+
+    fn record_debug(&mut self, field: &Field, _value: &dyn fmt::Debug) {
+        self.insert(field, FieldType::Text);
+    }
+}
+
+fn metadata_field_type(field: MetadataField) -> FieldType {
+    match field {
+        MetadataField::Line => FieldType::U64,
+        _ => FieldType::Text,
+    }
+}
+
+fn observe(observed_types: &mut BTreeMap<String, FieldType>, types: BTreeMap<String, FieldType>) {
+    for (field, field_type) in types {
+        observed_types
+            .entry(field)
+            .and_modify(|observed_type| *observed_type = widen(*observed_type, field_type))
+            .or_insert(field_type);
+    }
+}
+
+// The type able to hold the values of both types.
+fn widen(left: FieldType, right: FieldType) -> FieldType {
+    use FieldType::{F64, I64, U64};
+
+    match (left, right) {
+        _ if left == right => left,
+        (U64, I64) | (I64, U64) => I64,
+        (U64 | I64 | F64, U64 | I64 | F64) => F64,
+        _ => FieldType::Text,
+    }
+}
@@ -0,0 +1,81 @@
+use crate::defaults::DEFAULT_TIMESTAMP_FIELD;
+use crate::metadata::{MetadataField, MetadataLayout};
+use crate::span::SpanLayout;
+use crate::timestamp::TimestampFormat;
+use crate::trace::TraceExport;
+use std::time::SystemTime;
+use tracing_core::Metadata;
+
+// What the layer adds to the fields of an event to make up its document, and whether spans are
+// turned into documents of their own.
+#[derive(Debug, Clone)]
+pub(crate) struct DocumentConfig {
+    pub(crate) timestamp_field: Option<String>,
+    pub(crate) timestamp_format: TimestampFormat,
+    // Along with the keys they're added under.
+    pub(crate) metadata_fields: Vec<(MetadataField, String)>,
+    pub(crate) metadata_layout: MetadataLayout,
+    pub(crate) span_layout: SpanLayout,
+    pub(crate) trace_export: Option<TraceExport>,
+}
+
+impl Default for DocumentConfig {
+    fn default() -> Self {
+        Self {
+            timestamp_field: Some(DEFAULT_TIMESTAMP_FIELD.to_string()),
+            timestamp_format: TimestampFormat::default(),
+            metadata_fields: Vec::new(),
+            metadata_layout: MetadataLayout::default(),
+            span_layout: SpanLayout::default(),
+            trace_export: None,
+        }
+    }
+}
+
+impl DocumentConfig {
+    // A field of the event with the same name is kept instead.
+    pub(crate) fn insert_timestamp(
+        &self,
+        document: &mut serde_json::Map<String, serde_json::Value>,
+        time: SystemTime,
+    ) {
+        if let Some(timestamp_field) = &self.timestamp_field {
+            document
+                .entry(timestamp_field)
+                .or_insert_with(|| self.timestamp_format.format(time));
+        }
+    }
+
+    pub(crate) fn insert_metadata(
+        &self,
+        document: &mut serde_json::Map<String, serde_json::Value>,
+        metadata: &Metadata<'_>,
+    ) {
+        if self.metadata_fields.is_empty() {
+            return;
+        }
+        let metadata_fields = self
+            .metadata_fields
+            .iter()
+            .filter_map(|(field, key)| Some((key.clone(), field.value(metadata)?)));
+        match &self.metadata_layout {
+            MetadataLayout::Flattened => {
+                for (key, value) in metadata_fields {
+                    document.entry(key).or_insert(value);
+                }
+            }
+            MetadataLayout::Nested(key) => {
+                document
+                    .entry(key)
+                    .or_insert_with(|| metadata_fields.collect());
+            }
+        }
+    }
+
+    // Replaces the key of a field that was already added.
+    pub(crate) fn add_metadata_field(&mut self, field: MetadataField, key: String) {
+        self.metadata_fields
+            .retain(|(existing, _)| *existing != field);
+        self.metadata_fields.push((field, key));
+    }
+}
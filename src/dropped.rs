@@ -0,0 +1,131 @@
+use crate::backpressure::DropReason;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// How many events headed to an index were dropped for a reason since the layer was built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedEventsCount {
+    pub index_id: String,
+    pub reason: DropReason,
+    pub count: u64,
+}
+
+// Counters of dropped events, shared by the layer, the background task and the handle. Counts are
+// atomics, only the window of drops not reported yet is behind a mutex.
+#[derive(Debug)]
+pub(crate) struct DropCounters {
+    per_index: HashMap<String, IndexDropCounters>,
+    total: AtomicU64,
+}
+
+#[derive(Debug)]
+struct IndexDropCounters {
+    by_reason: [AtomicU64; DropReason::ALL.len()],
+    // Drops that haven't been reported to the index yet.
+    unreported: Mutex<Option<DropWindow>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct DropWindow {
+    count: u64,
+    first_dropped_at: SystemTime,
+    last_dropped_at: SystemTime,
+}
+
+impl DropCounters {
+    pub(crate) fn new(index_ids: impl IntoIterator<Item = String>) -> Self {
+        let per_index = index_ids
+            .into_iter()
+            .map(|index_id| {
+                let counters = IndexDropCounters {
+                    by_reason: Default::default(),
+                    unreported: Mutex::new(None),
+                };
+                (index_id, counters)
+            })
+            .collect();
+        Self {
+            per_index,
+            total: AtomicU64::new(0),
+        }
+    }
+
+    // Returns how many events were dropped in total, including this one.
+    pub(crate) fn record(&self, index_id: &str, reason: DropReason) -> u64 {
+        if let Some(counters) = self.per_index.get(index_id) {
+            counters.by_reason[reason as usize].fetch_add(1, Ordering::Relaxed);
+            let now = SystemTime::now();
+            let mut unreported = lock(&counters.unreported);
+            let window = unreported.get_or_insert(DropWindow {
+                count: 0,
+                first_dropped_at: now,
+                last_dropped_at: now,
+            });
+            window.count += 1;
+            window.last_dropped_at = now;
+        }
+        self.total.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub(crate) fn snapshot(&self) -> Vec<DroppedEventsCount> {
+        let mut snapshot = Vec::new();
+        for (index_id, counters) in self.per_index.iter() {
+            for reason in DropReason::ALL {
+                let count = counters.by_reason[reason as usize].load(Ordering::Relaxed);
+                if count > 0 {
+                    snapshot.push(DroppedEventsCount {
+                        index_id: index_id.clone(),
+                        reason,
+                        count,
+                    });
+                }
+            }
+        }
+        snapshot
+    }
+
+    pub(crate) fn take_unreported(&self, index_id: &str) -> Option<DropWindow> {
+        lock(&self.per_index.get(index_id)?.unreported).take()
+    }
+
+    // Puts back a window that couldn't be reported, merging it with the drops that happened since.
+    pub(crate) fn restore_unreported(&self, index_id: &str, window: DropWindow) {
+        let Some(counters) = self.per_index.get(index_id) else {
+            return;
+        };
+        let mut unreported = lock(&counters.unreported);
+        *unreported = Some(match unreported.take() {
+            Some(newer) => DropWindow {
+                count: window.count + newer.count,
+                first_dropped_at: window.first_dropped_at,
+                last_dropped_at: newer.last_dropped_at,
+            },
+            None => window,
+        });
+    }
+}
+
+impl DropWindow {
+    pub(crate) fn to_summary_document(&self) -> serde_json::Map<String, serde_json::Value> {
+        let first_dropped_at = humantime::format_rfc3339_millis(self.first_dropped_at).to_string();
+        let last_dropped_at = humantime::format_rfc3339_millis(self.last_dropped_at).to_string();
+        let message = format!(
+            "{} events dropped between {} and {}",
+            self.count, first_dropped_at, last_dropped_at,
+        );
+        let mut document = serde_json::Map::new();
+        document.insert("message".to_string(), message.into());
+        document.insert("dropped_events".to_string(), self.count.into());
+        document.insert("dropped_from".to_string(), first_dropped_at.into());
+        document.insert("dropped_until".to_string(), last_dropped_at.into());
+        document
+    }
+}
+
+fn lock(window: &Mutex<Option<DropWindow>>) -> MutexGuard<'_, Option<DropWindow>> {
+    window
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
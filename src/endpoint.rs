@@ -0,0 +1,29 @@
+use crate::validation::ConfigProblem;
+use url::Url;
+
+// Makes sure that joining paths to `url` keeps its path, e.g. the `/quickwit` prefix of a
+// Quickwit served behind a gateway. The query is kept as well and sent with every request.
+pub(crate) fn normalize_base_url(mut url: Url) -> Result<Url, ConfigProblem> {
+    if url.cannot_be_a_base() {
+        return Err(ConfigProblem::InvalidUrl {
+            url: url.to_string(),
+        });
+    }
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    url.set_fragment(None);
+    Ok(url)
+}
+
+// Percent-encodes `segments` and appends them to the path of `base_url`, which must have been
+// normalized.
+pub(crate) fn join(base_url: &Url, segments: &[&str]) -> Url {
+    let mut url = base_url.clone();
+    url.path_segments_mut()
+        .expect("Normalized base URL can be a base!")
+        .pop_if_empty()
+        .extend(segments);
+    url
+}
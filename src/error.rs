@@ -0,0 +1,174 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(crate) type ErrorHook = Arc<dyn Fn(QuickwitError) + Send + Sync + 'static>;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum QuickwitError {
+    /// The ingest request didn't reach Quickwit or its response couldn't be read.
+    Transport {
+        index_id: String,
+        batch_size: usize,
+        attempt: u32,
+        source: Box<dyn Error + Send + Sync + 'static>,
+    },
+    /// Quickwit answered the ingest request with a non-2xx status.
+    HttpStatus {
+        index_id: String,
+        batch_size: usize,
+        attempt: u32,
+        status: u16,
+        body: String,
+    },
+    /// A log couldn't be serialized into the ingest request body and was skipped.
+    Serialization {
+        index_id: String,
+        source: Box<dyn Error + Send + Sync + 'static>,
+    },
+    /// The event was dropped because the background task can't keep up.
+    ChannelFull { index_id: String },
+    /// The spill file couldn't be written to or read from.
+    Spill { source: io::Error },
+    /// The event's marker value isn't mapped to any index.
+    UnmappedMarker { marker_value: String },
+    /// Quickwit answered the ingest request with `404 Not Found`.
+    IndexUnknown {
+        index_id: String,
+        batch_size: usize,
+        attempt: u32,
+    },
+    /// Quickwit refused a single document of a bulk request it otherwise accepted.
+    DocumentRejected {
+        index_id: String,
+        status: u16,
+        error_type: String,
+        reason: String,
+    },
+    /// The index couldn't be created from its `IndexConfig`.
+    IndexCreation { index_id: String, reason: String },
+    /// The background task didn't shut down in time.
+    ShutdownTimeout { timeout: Duration },
+}
+
+impl QuickwitError {
+    pub fn index_id(&self) -> Option<&str> {
+        match self {
+            QuickwitError::Transport { index_id, .. }
+            | QuickwitError::HttpStatus { index_id, .. }
+            | QuickwitError::Serialization { index_id, .. }
+            | QuickwitError::ChannelFull { index_id }
+            | QuickwitError::IndexUnknown { index_id, .. }
+            | QuickwitError::DocumentRejected { index_id, .. }
+            | QuickwitError::IndexCreation { index_id, .. } => Some(index_id),
+            QuickwitError::Spill { .. }
+            | QuickwitError::UnmappedMarker { .. }
+            | QuickwitError::ShutdownTimeout { .. } => None,
+        }
+    }
+
+    pub fn batch_size(&self) -> Option<usize> {
+        match self {
+            QuickwitError::Transport { batch_size, .. }
+            | QuickwitError::HttpStatus { batch_size, .. }
+            | QuickwitError::IndexUnknown { batch_size, .. } => Some(*batch_size),
+            _ => None,
+        }
+    }
+
+    pub fn attempt(&self) -> Option<u32> {
+        match self {
+            QuickwitError::Transport { attempt, .. }
+            | QuickwitError::HttpStatus { attempt, .. }
+            | QuickwitError::IndexUnknown { attempt, .. } => Some(*attempt),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for QuickwitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuickwitError::Transport {
+                index_id,
+                batch_size,
+                attempt,
+                source,
+            } => write!(
+                f,
+                "failed to send {} logs to index `{}` (attempt {}): {}",
+                batch_size, index_id, attempt, source,
+            ),
+            QuickwitError::HttpStatus {
+                index_id,
+                batch_size,
+                attempt,
+                status,
+                body,
+            } => write!(
+                f,
+                "Quickwit rejected {} logs for index `{}` with status {} (attempt {}): {}",
+                batch_size, index_id, status, attempt, body,
+            ),
+            QuickwitError::Serialization { index_id, source } => {
+                write!(
+                    f,
+                    "failed to serialize a log for index `{}`: {}",
+                    index_id, source
+                )
+            }
+            QuickwitError::ChannelFull { index_id } => {
+                write!(f, "dropped a log for index `{}`: channel is full", index_id)
+            }
+            QuickwitError::Spill { source } => {
+                write!(f, "failed to use the spill file: {}", source)
+            }
+            QuickwitError::UnmappedMarker { marker_value } => {
+                write!(
+                    f,
+                    "marker value `{}` isn't mapped to any index",
+                    marker_value
+                )
+            }
+            QuickwitError::IndexUnknown {
+                index_id,
+                batch_size,
+                attempt,
+            } => write!(
+                f,
+                "index `{}` doesn't exist, {} logs were dropped (attempt {})",
+                index_id, batch_size, attempt,
+            ),
+            QuickwitError::DocumentRejected {
+                index_id,
+                status,
+                error_type,
+                reason,
+            } => write!(
+                f,
+                "Quickwit rejected a log for index `{}` with status {} ({}): {}",
+                index_id, status, error_type, reason,
+            ),
+            QuickwitError::IndexCreation { index_id, reason } => {
+                write!(f, "failed to create index `{}`: {}", index_id, reason)
+            }
+            QuickwitError::ShutdownTimeout { timeout } => {
+                write!(f, "background task didn't shut down within {:?}", timeout)
+            }
+        }
+    }
+}
+
+impl Error for QuickwitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QuickwitError::Transport { source, .. }
+            | QuickwitError::Serialization { source, .. } => Some(source.as_ref()),
+            QuickwitError::Spill { source } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::dropped::{DropCounters, DroppedEventsCount};
+use crate::error::QuickwitError;
+use crate::verify::VerifyError;
+use std::ops::AddAssign;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+pub(crate) enum Command {
+    Flush(oneshot::Sender<FlushReport>),
+    Shutdown(oneshot::Sender<FlushReport>),
+    Verify(oneshot::Sender<Result<(), VerifyError>>),
+    CreateIndexes(oneshot::Sender<Result<Vec<String>, QuickwitError>>),
+}
+
+/// Controls the background task returned from `QuickwitLoggingLayerBuilder::build`.
+#[derive(Debug, Clone)]
+pub struct QuickwitHandle {
+    commands: mpsc::UnboundedSender<Command>,
+    drop_counters: Arc<DropCounters>,
+}
+
+/// How many documents were sent to Quickwit successfully and how many were given up on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FlushReport {
+    pub delivered: usize,
+    pub lost: usize,
+}
+
+impl AddAssign for FlushReport {
+    fn add_assign(&mut self, other: Self) {
+        self.delivered += other.delivered;
+        self.lost += other.lost;
+    }
+}
+
+impl QuickwitHandle {
+    pub(crate) fn new(
+        commands: mpsc::UnboundedSender<Command>,
+        drop_counters: Arc<DropCounters>,
+    ) -> Self {
+        Self {
+            commands,
+            drop_counters,
+        }
+    }
+
+    /// Non-zero counts of events dropped before reaching the background task, per index and reason.
+    pub fn dropped_events(&self) -> Vec<DroppedEventsCount> {
+        self.drop_counters.snapshot()
+    }
+
+    /// Sends every buffered document to Quickwit, regardless of `batch_size` and flush interval.
+    ///
+    /// Returns an empty report if the background task isn't running anymore.
+    pub async fn flush(&self) -> FlushReport {
+        let (reply_sender, reply_receiver) = oneshot::channel();
+        if self.commands.send(Command::Flush(reply_sender)).is_err() {
+            return FlushReport::default();
+        }
+        reply_receiver.await.unwrap_or_default()
+    }
+
+    /// Checks that Quickwit is healthy and that every mapped index exists, e.g. before a service
+    /// starts accepting traffic. Requests are sent the way ingest requests are, with the same
+    /// credentials and timeouts, but aren't retried.
+    pub async fn verify(&self) -> Result<(), VerifyError> {
+        let (reply_sender, reply_receiver) = oneshot::channel();
+        if self.commands.send(Command::Verify(reply_sender)).is_err() {
+            return Err(VerifyError::Stopped);
+        }
+        reply_receiver.await.unwrap_or(Err(VerifyError::Stopped))
+    }
+
+    /// Creates the indexes having an `IndexConfig` that don't exist yet instead of waiting for
+    /// the first flush to them, and returns their ids.
+    ///
+    /// Returns no ids if the background task isn't running anymore.
+    pub async fn create_missing_indexes(&self) -> Result<Vec<String>, QuickwitError> {
+        let (reply_sender, reply_receiver) = oneshot::channel();
+        if self
+            .commands
+            .send(Command::CreateIndexes(reply_sender))
+            .is_err()
+        {
+            return Ok(Vec::new());
+        }
+        reply_receiver.await.unwrap_or(Ok(Vec::new()))
+    }
+
+    /// Stops accepting new events, flushes every buffered document and stops the background task.
+    ///
+    /// Fails with `QuickwitError::ShutdownTimeout` if the background task didn't finish within
+    /// `timeout`.
+    pub async fn shutdown(&self, timeout: Duration) -> Result<FlushReport, QuickwitError> {
+        let (reply_sender, reply_receiver) = oneshot::channel();
+        if self.commands.send(Command::Shutdown(reply_sender)).is_err() {
+            return Ok(FlushReport::default());
+        }
+        match tokio::time::timeout(timeout, reply_receiver).await {
+            Ok(reply) => Ok(reply.unwrap_or_default()),
+            Err(_) => Err(QuickwitError::ShutdownTimeout { timeout }),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use reqwest::{Certificate, Client, Identity};
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// How the HTTP client talking to Quickwit is obtained.
+#[derive(Default)]
+pub(crate) struct HttpClientConfig {
+    pub(crate) client: Option<Client>,
+    pub(crate) request_timeout: Option<Duration>,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) ca_bundle: Option<CaBundle>,
+    pub(crate) identity: Option<Identity>,
+}
+
+pub(crate) enum CaBundle {
+    Pem(Vec<u8>),
+    File(PathBuf),
+}
+
+impl HttpClientConfig {
+    // The builder options a custom client makes inert.
+    pub(crate) fn overridden_options(&self) -> Vec<&'static str> {
+        if self.client.is_none() {
+            return Vec::new();
+        }
+        let ca_bundle_option = match self.ca_bundle {
+            Some(CaBundle::Pem(_)) => Some("with_ca_bundle"),
+            Some(CaBundle::File(_)) => Some("with_ca_bundle_file"),
+            None => None,
+        };
+        [
+            self.connect_timeout.map(|_| "with_connect_timeout"),
+            ca_bundle_option,
+            self.identity.as_ref().map(|_| "with_client_identity"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    // The request timeout isn't set on the client so that it applies to a custom one as well.
+    pub(crate) fn build_client(self) -> Result<Client, Box<dyn Error + Send + Sync + 'static>> {
+        if let Some(client) = self.client {
+            return Ok(client);
+        }
+        let mut client_builder = Client::builder();
+        if let Some(connect_timeout) = self.connect_timeout {
+            client_builder = client_builder.connect_timeout(connect_timeout);
+        }
+        if let Some(ca_bundle) = self.ca_bundle {
+            let pem_bundle = match ca_bundle {
+                CaBundle::Pem(pem_bundle) => pem_bundle,
+                CaBundle::File(path) => std::fs::read(path)?,
+            };
+            for certificate in Certificate::from_pem_bundle(&pem_bundle)? {
+                client_builder = client_builder.add_root_certificate(certificate);
+            }
+        }
+        if let Some(identity) = self.identity {
+            client_builder = client_builder.identity(identity);
+        }
+        Ok(client_builder.build()?)
+    }
+}
@@ -0,0 +1,150 @@
+use crate::timestamp::TimestampFormat;
+use serde::Serialize;
+use serde_json::json;
+use std::time::Duration;
+
+// The version of Quickwit's index config format the generated configs follow.
+const INDEX_CONFIG_VERSION: &str = "0.8";
+
+/// How Quickwit creates an index that doesn't exist yet, see `with_index_config`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexConfig {
+    mode: DocMappingMode,
+    field_mappings: Vec<FieldMapping>,
+    timestamp_field: Option<String>,
+    timestamp_format: Option<TimestampFormat>,
+    retention_period: Option<Duration>,
+}
+
+/// What Quickwit does with fields missing from the doc mapping.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocMappingMode {
+    /// Indexes them and makes them searchable.
+    #[default]
+    Dynamic,
+    /// Stores them without indexing them.
+    Lenient,
+    /// Rejects documents having them.
+    Strict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Text,
+    I64,
+    U64,
+    F64,
+    Bool,
+    Datetime,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldMapping {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    /// Whether the field is stored in a columnar format, needed for aggregations and sorting.
+    pub fast: bool,
+}
+
+impl FieldMapping {
+    pub fn new(name: impl Into<String>, field_type: FieldType) -> Self {
+        Self {
+            name: name.into(),
+            field_type,
+            fast: false,
+        }
+    }
+
+    pub fn fast(mut self) -> Self {
+        self.fast = true;
+        self
+    }
+}
+
+impl IndexConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mode(mut self, mode: DocMappingMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Replaces the mapping of a field having the same name.
+    pub fn with_field_mapping(mut self, field_mapping: FieldMapping) -> Self {
+        self.field_mappings
+            .retain(|existing| existing.name != field_mapping.name);
+        self.field_mappings.push(field_mapping);
+        self
+    }
+
+    /// Makes `field` the index's timestamp field, mapping it as a fast datetime field accepting
+    /// RFC 3339 strings and Unix timestamps.
+    pub fn with_timestamp_field(mut self, field: impl Into<String>) -> Self {
+        let field = field.into();
+        self = self.with_field_mapping(FieldMapping::new(&field, FieldType::Datetime).fast());
+        self.timestamp_field = Some(field);
+        self
+    }
+
+    /// Makes the timestamp field only accept timestamps written in `format`, and return them the
+    /// same way in search results.
+    pub fn with_timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.timestamp_format = Some(format);
+        self
+    }
+
+    /// Makes Quickwit delete splits older than `period`. Requires a timestamp field.
+    pub fn with_retention(mut self, period: Duration) -> Self {
+        self.retention_period = Some(period);
+        self
+    }
+
+    pub fn field_mappings(&self) -> &[FieldMapping] {
+        &self.field_mappings
+    }
+
+    /// The body of the `api/v1/indexes` request creating the index.
+    pub fn to_json(&self, index_id: &str) -> serde_json::Value {
+        let field_mappings = self
+            .field_mappings
+            .iter()
+            .map(|field_mapping| {
+                let mut mapping = json!(field_mapping);
+                if field_mapping.field_type == FieldType::Datetime {
+                    mapping["input_formats"] = json!(["rfc3339", "unix_timestamp"]);
+                }
+                let is_timestamp_field = self.timestamp_field.as_ref() == Some(&field_mapping.name);
+                if let (true, Some(format)) = (is_timestamp_field, self.timestamp_format) {
+                    mapping["input_formats"] = json!([format.input_format()]);
+                    mapping["output_format"] = json!(format.output_format());
+                }
+                mapping
+            })
+            .collect::<Vec<_>>();
+        let mut doc_mapping = json!({
+            "mode": self.mode,
+            "field_mappings": field_mappings,
+        });
+        if let Some(timestamp_field) = &self.timestamp_field {
+            doc_mapping["timestamp_field"] = json!(timestamp_field);
+        }
+        let mut index_config = json!({
+            "version": INDEX_CONFIG_VERSION,
+            "index_id": index_id,
+            "doc_mapping": doc_mapping,
+        });
+        if let Some(retention_period) = self.retention_period {
+            index_config["retention"] = json!({
+                "period": humantime::format_duration(retention_period).to_string(),
+                "schedule": "hourly",
+            });
+        }
+        index_config
+    }
+}
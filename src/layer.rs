@@ -1,10 +1,19 @@
+use crate::backpressure::EventSender;
+#[cfg(feature = "opentelemetry")]
+use crate::correlation;
+use crate::document::DocumentConfig;
+use crate::error::{ErrorHook, QuickwitError};
 use crate::message::QuickwitLogMessage;
+use crate::span::{self, SpanFields, SpanLayout};
+use crate::trace::SpanRecord;
 use crate::visitor::{LogVisitor, TargetFieldVisitor};
 use std::collections::HashMap;
-use tokio::sync::mpsc;
+use std::time::SystemTime;
+use tracing_core::span::{Attributes, Id, Record};
 use tracing_core::Event;
 use tracing_core::Subscriber;
 use tracing_subscriber::layer::Context as TracingContext;
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::Layer;
 
 #[cfg(feature = "testing-extras")]
@@ -15,12 +24,12 @@ use std::sync::Arc;
 use tokio::sync::Notify;
 
 pub struct QuickwitLoggingLayer {
-    // TODO: Maybe use single producer?
-    sender: mpsc::Sender<QuickwitLogMessage>,
+    sender: EventSender,
     target_field: String,
     // TODO: Consider `&' static` instead of `String`.
     field_to_index: HashMap<String, String>,
-    on_index_missing: Box<dyn Fn() + Send + Sync + 'static>,
+    document_config: DocumentConfig,
+    on_error: ErrorHook,
     #[cfg(feature = "testing-extras")]
     emitted_events_count: Arc<AtomicUsize>,
     #[cfg(feature = "testing-extras")]
@@ -31,10 +40,11 @@ pub struct QuickwitLoggingLayer {
 
 impl QuickwitLoggingLayer {
     pub(crate) fn new(
-        sender: mpsc::Sender<QuickwitLogMessage>,
+        sender: EventSender,
         target_field: String,
         field_to_index: HashMap<String, String>,
-        on_index_missing: Box<dyn Fn() + Send + Sync + 'static>,
+        document_config: DocumentConfig,
+        on_error: ErrorHook,
         #[cfg(feature = "testing-extras")] expected_emitted_events_count: usize,
         #[cfg(feature = "testing-extras")] emitted_all: Arc<Notify>,
     ) -> Self {
@@ -44,7 +54,8 @@ impl QuickwitLoggingLayer {
             sender,
             target_field,
             field_to_index,
-            on_index_missing,
+            document_config,
+            on_error,
             #[cfg(feature = "testing-extras")]
             expected_emitted_events_count,
             #[cfg(feature = "testing-extras")]
@@ -68,39 +79,156 @@ impl QuickwitLoggingLayer {
     }
 }
 
-impl<S: Subscriber> Layer<S> for QuickwitLoggingLayer {
-    fn on_event(&self, event: &Event<'_>, _ctx: TracingContext<'_, S>) {
+impl QuickwitLoggingLayer {
+    // Adds the event to the span it happened in, whether the event is routed to an index or not.
+    fn record_span_event<S>(&self, event: &Event<'_>, ctx: &TracingContext<'_, S>)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(span_record) = extensions.get_mut::<SpanRecord>() {
+            let mut visitor = LogVisitor::new();
+            event.record(&mut visitor);
+            span_record.record_event(event, visitor.log);
+        }
+    }
+}
+
+impl Drop for QuickwitLoggingLayer {
+    fn drop(&mut self) {
+        self.sender.close();
+    }
+}
+
+impl<S> Layer<S> for QuickwitLoggingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: TracingContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut target_field_visitor = TargetFieldVisitor::new(&self.target_field);
+        attrs.record(&mut target_field_visitor);
+        if let Some(target_value) = target_field_visitor.target_value {
+            span::record_marker(&span, &self.target_field, target_value);
+        }
+        let exports_traces = self
+            .document_config
+            .trace_export
+            .as_ref()
+            .is_some_and(|trace_export| trace_export.exports(span.metadata()));
+        if self.document_config.span_layout == SpanLayout::Omitted && !exports_traces {
+            return;
+        }
+        let mut visitor = LogVisitor::new();
+        attrs.record(&mut visitor);
+        if exports_traces {
+            let exported_ancestor = span
+                .scope()
+                .skip(1)
+                .find(|ancestor| ancestor.extensions().get::<SpanRecord>().is_some());
+            let span_record = match exported_ancestor {
+                Some(ancestor) => SpanRecord::new(
+                    ancestor.extensions().get::<SpanRecord>(),
+                    visitor.log.clone(),
+                ),
+                None => SpanRecord::new(None, visitor.log.clone()),
+            };
+            span.extensions_mut().replace(span_record);
+        }
+        // Another `QuickwitLoggingLayer` may have inserted them already.
+        span.extensions_mut().replace(SpanFields(visitor.log));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: TracingContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut target_field_visitor = TargetFieldVisitor::new(&self.target_field);
+        values.record(&mut target_field_visitor);
+        if let Some(target_value) = target_field_visitor.target_value {
+            span::record_marker(&span, &self.target_field, target_value);
+        }
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<SpanFields>() {
+            fields.record(values);
+        }
+        if let Some(span_record) = extensions.get_mut::<SpanRecord>() {
+            let mut visitor = LogVisitor {
+                log: std::mem::take(&mut span_record.attributes),
+            };
+            values.record(&mut visitor);
+            span_record.attributes = visitor.log;
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: TracingContext<'_, S>) {
+        let Some(trace_export) = &self.document_config.trace_export else {
+            return;
+        };
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(span_record) = span.extensions_mut().remove::<SpanRecord>() else {
+            return;
+        };
+        let log_message = QuickwitLogMessage {
+            index_id: trace_export.index_id.clone(),
+            log: span_record.into_document(span.metadata(), trace_export),
+        };
+        self.sender.send(log_message);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: TracingContext<'_, S>) {
+        let recorded_at = SystemTime::now();
         #[cfg(feature = "testing-extras")]
         self.increment_emitted_events_count();
         #[cfg(feature = "testing-extras")]
         self.notify_if_emitted_expected_events_count();
-        let mut visitor = LogVisitor::new();
-        let maybe_marker_field = event
-            .fields()
-            .find(|field| field.name() == self.target_field);
-        if maybe_marker_field.is_none() {
-            return;
+        if self.document_config.trace_export.is_some() {
+            self.record_span_event(event, &ctx);
         }
-        let marker_field = maybe_marker_field.unwrap().name();
-        let mut target_field_visitor = TargetFieldVisitor::new(marker_field);
+        let mut visitor = LogVisitor::new();
+        // The event's own marker wins over the ones of the spans it happened in.
+        let mut target_field_visitor = TargetFieldVisitor::new(&self.target_field);
         event.record(&mut target_field_visitor);
-        if target_field_visitor.target_value.is_none() {
+        let maybe_target_value = target_field_visitor.target_value.or_else(|| {
+            let scope = ctx.event_scope(event)?;
+            span::inherited_marker(scope, &self.target_field)
+        });
+        let Some(target_value) = maybe_target_value else {
             return;
-        }
-        let target_value = target_field_visitor.target_value.unwrap();
+        };
         let maybe_index_id = self.field_to_index.get(&target_value);
         if maybe_index_id.is_none() {
-            (self.on_index_missing)();
+            (self.on_error)(QuickwitError::UnmappedMarker {
+                marker_value: target_value,
+            });
             return;
         }
         let index_id = maybe_index_id.unwrap().to_owned();
         event.record(&mut visitor);
+        self.document_config
+            .insert_timestamp(&mut visitor.log, recorded_at);
+        self.document_config
+            .insert_metadata(&mut visitor.log, event.metadata());
+        if let Some(scope) = ctx.event_scope(event) {
+            self.document_config
+                .span_layout
+                .insert_spans(&mut visitor.log, scope);
+        }
+        #[cfg(feature = "opentelemetry")]
+        if let Some(span) = ctx.event_span(event) {
+            correlation::insert_trace_context(&mut visitor.log, &span);
+        }
         let log_message = QuickwitLogMessage {
             index_id,
             log: visitor.log,
         };
-        // TODO: Let the client configure sending strategy (blocking or non-blocking, timeout,
-        // `on_error` callback etc.).
-        let _ = self.sender.try_send(log_message);
+        self.sender.send(log_message);
     }
 }
@@ -1,8 +1,51 @@
+mod auth;
+mod backpressure;
 mod builder;
+mod commit;
+mod compression;
+#[cfg(feature = "opentelemetry")]
+mod correlation;
 mod defaults;
+mod doc_mapping;
+mod document;
+mod dropped;
+mod endpoint;
+mod error;
+mod handle;
+mod http;
+mod index_config;
 mod layer;
 mod message;
+mod metadata;
 mod ndjson;
+mod protocol;
+mod queue;
+mod report;
+mod retry;
+mod span;
+mod spill;
+mod timestamp;
+mod trace;
+mod validation;
+mod verify;
 mod visitor;
+mod worker;
 
+pub use backpressure::{BackpressurePolicy, DropReason, DroppedEvent};
 pub use builder::QuickwitLoggingLayerBuilder;
+pub use commit::CommitMode;
+pub use compression::Compression;
+pub use doc_mapping::DocMappingRecorder;
+pub use dropped::DroppedEventsCount;
+pub use error::QuickwitError;
+pub use handle::{FlushReport, QuickwitHandle};
+pub use index_config::{DocMappingMode, FieldMapping, FieldType, IndexConfig};
+pub use metadata::{MetadataField, MetadataLayout};
+pub use protocol::IngestProtocol;
+pub use report::{IngestReport, ParseFailure};
+pub use retry::{RetryPolicy, StatusClass};
+pub use span::SpanLayout;
+pub use timestamp::TimestampFormat;
+pub use trace::TraceExport;
+pub use validation::{ConfigError, ConfigProblem};
+pub use verify::VerifyError;
@@ -1,6 +1,6 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct QuickwitLogMessage {
     pub(crate) index_id: String,
     pub(crate) log: serde_json::Map<String, serde_json::Value>,
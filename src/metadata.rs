@@ -0,0 +1,58 @@
+use tracing_core::Metadata;
+
+/// Metadata of an event that can be added to its document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetadataField {
+    /// E.g. `INFO`.
+    Level,
+    Target,
+    ModulePath,
+    File,
+    Line,
+    /// The name of the call site, e.g. `event src/main.rs:12`.
+    Name,
+}
+
+/// Where the metadata fields are added to a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MetadataLayout {
+    /// Next to the fields of the event, which win over metadata fields having the same key.
+    #[default]
+    Flattened,
+    /// Into an object under the given key, e.g. `metadata`.
+    Nested(String),
+}
+
+impl MetadataField {
+    pub const ALL: [MetadataField; 6] = [
+        MetadataField::Level,
+        MetadataField::Target,
+        MetadataField::ModulePath,
+        MetadataField::File,
+        MetadataField::Line,
+        MetadataField::Name,
+    ];
+
+    /// The key the field is added under unless configured otherwise.
+    pub fn default_key(self) -> &'static str {
+        match self {
+            MetadataField::Level => "level",
+            MetadataField::Target => "target",
+            MetadataField::ModulePath => "module_path",
+            MetadataField::File => "file",
+            MetadataField::Line => "line",
+            MetadataField::Name => "name",
+        }
+    }
+
+    pub(crate) fn value(self, metadata: &Metadata<'_>) -> Option<serde_json::Value> {
+        match self {
+            MetadataField::Level => Some(metadata.level().as_str().into()),
+            MetadataField::Target => Some(metadata.target().into()),
+            MetadataField::ModulePath => metadata.module_path().map(Into::into),
+            MetadataField::File => metadata.file().map(Into::into),
+            MetadataField::Line => metadata.line().map(Into::into),
+            MetadataField::Name => Some(metadata.name().into()),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::commit::CommitMode;
+use crate::endpoint;
+use crate::error::QuickwitError;
+use crate::ndjson;
+use crate::report::{IngestReport, IngestResponse, ParseFailure};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io;
+use url::Url;
+
+/// Which Quickwit API the documents are sent to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IngestProtocol {
+    /// `api/v1/{index_id}/ingest`.
+    #[default]
+    Native,
+    /// The Elasticsearch-compatible `api/v1/_elastic/_bulk`. Every flush sends the buffers of
+    /// all indexes in a single request, committed the strictest way any of them is configured.
+    ElasticBulk,
+    /// `api/v1/{index_id}/ingest` of a Quickwit cluster running ingest V2, asking for a detailed
+    /// response with the reason every rejected document was rejected for.
+    IngestV2,
+}
+
+impl IngestProtocol {
+    pub(crate) fn endpoint(
+        self,
+        quickwit_url: &Url,
+        index_id: &str,
+        commit_mode: CommitMode,
+    ) -> Url {
+        let mut endpoint = match self {
+            IngestProtocol::Native | IngestProtocol::IngestV2 => {
+                endpoint::join(quickwit_url, &["api", "v1", index_id, "ingest"])
+            }
+            IngestProtocol::ElasticBulk => {
+                endpoint::join(quickwit_url, &["api", "v1", "_elastic", "_bulk"])
+            }
+        };
+        {
+            let mut query = endpoint.query_pairs_mut();
+            match self {
+                IngestProtocol::Native | IngestProtocol::IngestV2 => {
+                    if commit_mode != CommitMode::Auto {
+                        query.append_pair("commit", commit_mode.as_str());
+                    }
+                }
+                IngestProtocol::ElasticBulk => {
+                    if let Some(refresh) = commit_mode.as_elastic_refresh() {
+                        query.append_pair("refresh", refresh);
+                    }
+                }
+            }
+            if self == IngestProtocol::IngestV2 {
+                query.append_pair("detailed_response", "true");
+            }
+        }
+        // An empty query would leave a trailing `?` behind.
+        if endpoint.query() == Some("") {
+            endpoint.set_query(None);
+        }
+        endpoint
+    }
+
+    pub(crate) fn serialize_document(
+        self,
+        body: &mut Vec<u8>,
+        index_id: &str,
+        document: &serde_json::Map<String, serde_json::Value>,
+    ) -> io::Result<()> {
+        if self != IngestProtocol::ElasticBulk {
+            return ndjson::serialize(body, document);
+        }
+        // Serialized first so that a failure doesn't leave an action without a document behind.
+        let document = serde_json::to_vec(document)?;
+        ndjson::serialize(&mut *body, &json!({"create": {"_index": index_id}}))?;
+        body.extend_from_slice(&document);
+        body.push(b'\n');
+        Ok(())
+    }
+
+    // Returns a report per index the documents were sent to along with the documents rejected
+    // one by one, or nothing if the response isn't of the expected shape.
+    pub(crate) fn parse_response(
+        self,
+        response_body: &str,
+        batch_sizes: &HashMap<String, usize>,
+    ) -> (Vec<IngestReport>, Vec<QuickwitError>) {
+        match self {
+            IngestProtocol::Native | IngestProtocol::IngestV2 => {
+                let Some((index_id, batch_size)) = batch_sizes.iter().next() else {
+                    return (Vec::new(), Vec::new());
+                };
+                match serde_json::from_str::<IngestResponse>(response_body) {
+                    Ok(response) => (
+                        vec![response.into_report(index_id, *batch_size)],
+                        Vec::new(),
+                    ),
+                    Err(_) => (Vec::new(), Vec::new()),
+                }
+            }
+            IngestProtocol::ElasticBulk => {
+                match serde_json::from_str::<BulkResponse>(response_body) {
+                    Ok(response) => response.into_reports(batch_sizes),
+                    Err(_) => (Vec::new(), Vec::new()),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BulkResponse {
+    items: Vec<HashMap<String, BulkItem>>,
+}
+
+#[derive(Deserialize)]
+struct BulkItem {
+    #[serde(rename = "_index")]
+    index_id: String,
+    status: u16,
+    #[serde(default)]
+    error: Option<BulkItemError>,
+}
+
+#[derive(Deserialize)]
+struct BulkItemError {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    reason: String,
+}
+
+impl BulkResponse {
+    fn into_reports(
+        self,
+        batch_sizes: &HashMap<String, usize>,
+    ) -> (Vec<IngestReport>, Vec<QuickwitError>) {
+        let mut reports = HashMap::<String, IngestReport>::new();
+        let mut rejections = Vec::new();
+        for item in self.items.into_iter().flat_map(HashMap::into_values) {
+            let report = reports
+                .entry(item.index_id.clone())
+                .or_insert_with(|| IngestReport {
+                    index_id: item.index_id.clone(),
+                    batch_size: batch_sizes.get(&item.index_id).copied().unwrap_or_default(),
+                    num_docs_for_processing: 0,
+                    num_ingested_docs: Some(0),
+                    num_rejected_docs: Some(0),
+                    parse_failures: Vec::new(),
+                });
+            report.num_docs_for_processing += 1;
+            if (200..300).contains(&item.status) {
+                *report.num_ingested_docs.get_or_insert(0) += 1;
+                continue;
+            }
+            *report.num_rejected_docs.get_or_insert(0) += 1;
+            let error = item.error.unwrap_or(BulkItemError {
+                kind: String::new(),
+                reason: String::new(),
+            });
+            rejections.push(QuickwitError::DocumentRejected {
+                index_id: item.index_id,
+                status: item.status,
+                error_type: error.kind.clone(),
+                reason: error.reason.clone(),
+            });
+            report.parse_failures.push(ParseFailure {
+                document: String::new(),
+                message: error.reason,
+                reason: error.kind,
+            });
+        }
+        (reports.into_values().collect(), rejections)
+    }
+}
@@ -0,0 +1,139 @@
+use crate::message::QuickwitLogMessage;
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+// A bounded multi-producer single-consumer queue. Unlike `tokio::sync::mpsc` it lets producers
+// evict the oldest message and block the current thread for a limited time.
+pub(crate) struct EventQueue {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    readable: Notify,
+    writable: Condvar,
+}
+
+struct QueueState {
+    messages: VecDeque<QuickwitLogMessage>,
+    closed: bool,
+}
+
+pub(crate) enum PushError {
+    Full(QuickwitLogMessage),
+    Closed,
+}
+
+impl EventQueue {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                messages: VecDeque::with_capacity(capacity),
+                closed: false,
+            }),
+            capacity: capacity.max(1),
+            readable: Notify::new(),
+            writable: Condvar::new(),
+        }
+    }
+
+    pub(crate) fn try_push(&self, message: QuickwitLogMessage) -> Result<(), PushError> {
+        let state = self.lock();
+        if state.closed {
+            return Err(PushError::Closed);
+        }
+        if state.messages.len() >= self.capacity {
+            return Err(PushError::Full(message));
+        }
+        self.push_locked(state, message);
+        Ok(())
+    }
+
+    // Returns the evicted message, if any.
+    pub(crate) fn push_evicting_oldest(
+        &self,
+        message: QuickwitLogMessage,
+    ) -> Result<Option<QuickwitLogMessage>, PushError> {
+        let mut state = self.lock();
+        if state.closed {
+            return Err(PushError::Closed);
+        }
+        let evicted = if state.messages.len() >= self.capacity {
+            state.messages.pop_front()
+        } else {
+            None
+        };
+        self.push_locked(state, message);
+        Ok(evicted)
+    }
+
+    pub(crate) fn push_blocking(
+        &self,
+        message: QuickwitLogMessage,
+        timeout: Duration,
+    ) -> Result<(), PushError> {
+        let state = self.lock();
+        let (state, _) = self
+            .writable
+            .wait_timeout_while(state, timeout, |state| {
+                !state.closed && state.messages.len() >= self.capacity
+            })
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.closed {
+            return Err(PushError::Closed);
+        }
+        if state.messages.len() >= self.capacity {
+            return Err(PushError::Full(message));
+        }
+        self.push_locked(state, message);
+        Ok(())
+    }
+
+    pub(crate) async fn recv(&self) -> Option<QuickwitLogMessage> {
+        loop {
+            let readable = self.readable.notified();
+            {
+                let mut state = self.lock();
+                if let Some(message) = state.messages.pop_front() {
+                    self.writable.notify_one();
+                    return Some(message);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            readable.await;
+        }
+    }
+
+    pub(crate) fn try_recv(&self) -> Option<QuickwitLogMessage> {
+        let message = self.lock().messages.pop_front();
+        if message.is_some() {
+            self.writable.notify_one();
+        }
+        message
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lock().messages.is_empty()
+    }
+
+    // Messages that are already in the queue can still be received.
+    pub(crate) fn close(&self) {
+        self.lock().closed = true;
+        self.readable.notify_one();
+        self.writable.notify_all();
+    }
+
+    fn push_locked(&self, mut state: MutexGuard<'_, QueueState>, message: QuickwitLogMessage) {
+        state.messages.push_back(message);
+        drop(state);
+        self.readable.notify_one();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        // Nothing can panic while the lock is held, but a user's hook may, so don't propagate it.
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
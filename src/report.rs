@@ -0,0 +1,67 @@
+use serde::Deserialize;
+
+/// Why Quickwit refused to index a document.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ParseFailure {
+    #[serde(default)]
+    pub document: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// What Quickwit did with a batch it accepted, as reported in the ingest response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngestReport {
+    pub index_id: String,
+    /// Documents sent, including the summary of dropped events if there was one.
+    pub batch_size: usize,
+    pub num_docs_for_processing: u64,
+    /// Only reported by newer Quickwit versions.
+    pub num_ingested_docs: Option<u64>,
+    /// Only reported by newer Quickwit versions.
+    pub num_rejected_docs: Option<u64>,
+    pub parse_failures: Vec<ParseFailure>,
+}
+
+impl IngestReport {
+    pub fn accepted(&self) -> u64 {
+        self.num_ingested_docs
+            .unwrap_or(self.num_docs_for_processing)
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.num_rejected_docs
+            .unwrap_or_else(|| match self.num_ingested_docs {
+                Some(num_ingested_docs) => self
+                    .num_docs_for_processing
+                    .saturating_sub(num_ingested_docs),
+                None => (self.batch_size as u64).saturating_sub(self.num_docs_for_processing),
+            })
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct IngestResponse {
+    num_docs_for_processing: u64,
+    #[serde(default)]
+    num_ingested_docs: Option<u64>,
+    #[serde(default)]
+    num_rejected_docs: Option<u64>,
+    #[serde(default)]
+    parse_failures: Vec<ParseFailure>,
+}
+
+impl IngestResponse {
+    pub(crate) fn into_report(self, index_id: &str, batch_size: usize) -> IngestReport {
+        IngestReport {
+            index_id: index_id.to_string(),
+            batch_size,
+            num_docs_for_processing: self.num_docs_for_processing,
+            num_ingested_docs: self.num_ingested_docs,
+            num_rejected_docs: self.num_rejected_docs,
+            parse_failures: self.parse_failures,
+        }
+    }
+}
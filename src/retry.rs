@@ -0,0 +1,116 @@
+use crate::defaults::{
+    DEFAULT_RETRY_BASE_BACKOFF, DEFAULT_RETRY_MAX_ATTEMPTS, DEFAULT_RETRY_MAX_BACKOFF,
+};
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// A class of HTTP statuses Quickwit may answer with that is worth retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusClass {
+    /// `408 Request Timeout`.
+    RequestTimeout,
+    /// `429 Too Many Requests`.
+    TooManyRequests,
+    /// Any `4xx` status but `404 Not Found`, which is reported as `QuickwitError::IndexUnknown`
+    /// and never retried.
+    ClientError,
+    /// Any `5xx` status.
+    ServerError,
+}
+
+impl StatusClass {
+    fn contains(self, status: StatusCode) -> bool {
+        match self {
+            StatusClass::RequestTimeout => status == StatusCode::REQUEST_TIMEOUT,
+            StatusClass::TooManyRequests => status == StatusCode::TOO_MANY_REQUESTS,
+            StatusClass::ClientError => status.is_client_error(),
+            StatusClass::ServerError => status.is_server_error(),
+        }
+    }
+}
+
+/// Decides how many times and how often a batch is resent before it's given up on.
+///
+/// Transport errors (connection refused, timeouts etc.) are always retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    retry_on: Vec<StatusClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_RETRY_MAX_ATTEMPTS,
+            base_backoff: DEFAULT_RETRY_BASE_BACKOFF,
+            max_backoff: DEFAULT_RETRY_MAX_BACKOFF,
+            jitter: true,
+            retry_on: vec![
+                StatusClass::RequestTimeout,
+                StatusClass::TooManyRequests,
+                StatusClass::ServerError,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Gives up on a batch right after the first failed attempt.
+    pub fn never() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    /// Total number of attempts, including the first one.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_base_backoff(mut self, base_backoff: Duration) -> Self {
+        self.base_backoff = base_backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Randomizes every backoff between its half and its full length.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn retry_on(mut self, status_classes: impl IntoIterator<Item = StatusClass>) -> Self {
+        self.retry_on = status_classes.into_iter().collect();
+        self
+    }
+
+    pub(crate) fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub(crate) fn is_retryable(&self, status: StatusCode) -> bool {
+        self.retry_on
+            .iter()
+            .any(|status_class| status_class.contains(status))
+    }
+
+    // `failed_attempts` starts at 1.
+    pub(crate) fn backoff(&self, failed_attempts: u32) -> Duration {
+        let exponent = failed_attempts.saturating_sub(1).min(31);
+        let backoff = self
+            .base_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        if !self.jitter {
+            return backoff;
+        }
+        let half = backoff / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
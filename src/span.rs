@@ -0,0 +1,102 @@
+use crate::visitor::LogVisitor;
+use serde_json::json;
+use std::collections::HashMap;
+use tracing_core::span::Record;
+use tracing_core::Subscriber;
+use tracing_subscriber::registry::{LookupSpan, Scope, SpanRef};
+
+/// How the fields of the spans an event happened in are added to its document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SpanLayout {
+    /// Next to the fields of the event. The fields of the event win over the fields of spans,
+    /// and the fields of a span win over the fields of the spans it's in.
+    #[default]
+    Flattened,
+    /// Into an array under the given key, e.g. `spans`, of objects holding the name and the
+    /// fields of every span, starting from the root.
+    Nested(String),
+    /// Not at all.
+    Omitted,
+}
+
+// Stored in the extensions of every span.
+pub(crate) struct SpanFields(pub(crate) serde_json::Map<String, serde_json::Value>);
+
+// Values of marker fields recorded on a span, keyed by field name so that layers using different
+// marker fields can share it.
+pub(crate) struct SpanMarkers(HashMap<String, String>);
+
+pub(crate) fn record_marker<S>(span: &SpanRef<'_, S>, target_field: &str, target_value: String)
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let mut extensions = span.extensions_mut();
+    match extensions.get_mut::<SpanMarkers>() {
+        Some(SpanMarkers(markers)) => {
+            markers.insert(target_field.to_string(), target_value);
+        }
+        None => {
+            let markers = HashMap::from([(target_field.to_string(), target_value)]);
+            extensions.insert(SpanMarkers(markers));
+        }
+    }
+}
+
+// The marker value recorded on the nearest span having one.
+pub(crate) fn inherited_marker<S>(scope: Scope<'_, S>, target_field: &str) -> Option<String>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    scope.into_iter().find_map(|span| {
+        let extensions = span.extensions();
+        let SpanMarkers(markers) = extensions.get::<SpanMarkers>()?;
+        markers.get(target_field).cloned()
+    })
+}
+
+impl SpanFields {
+    pub(crate) fn record(&mut self, values: &Record<'_>) {
+        let mut visitor = LogVisitor {
+            log: std::mem::take(&mut self.0),
+        };
+        values.record(&mut visitor);
+        self.0 = visitor.log;
+    }
+}
+
+impl SpanLayout {
+    pub(crate) fn insert_spans<S>(
+        &self,
+        document: &mut serde_json::Map<String, serde_json::Value>,
+        scope: Scope<'_, S>,
+    ) where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        match self {
+            SpanLayout::Flattened => {
+                for span in scope {
+                    if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>() {
+                        for (key, value) in fields {
+                            document.entry(key).or_insert_with(|| value.clone());
+                        }
+                    }
+                }
+            }
+            SpanLayout::Nested(key) => {
+                let spans = scope
+                    .from_root()
+                    .map(|span| {
+                        let extensions = span.extensions();
+                        let fields = extensions
+                            .get::<SpanFields>()
+                            .map(|SpanFields(fields)| fields.clone())
+                            .unwrap_or_default();
+                        json!({"name": span.name(), "fields": fields})
+                    })
+                    .collect::<Vec<_>>();
+                document.entry(key).or_insert_with(|| spans.into());
+            }
+            SpanLayout::Omitted => {}
+        }
+    }
+}
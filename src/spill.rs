@@ -0,0 +1,66 @@
+use crate::message::QuickwitLogMessage;
+use crate::ndjson;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// NDJSON file holding the messages that didn't fit into the queue until the background task
+// catches up.
+pub(crate) struct SpillFile {
+    path: PathBuf,
+    lock: Mutex<()>,
+    has_messages: AtomicBool,
+}
+
+impl SpillFile {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        // Messages spilled by a previous run are picked up too.
+        let has_messages = fs::metadata(&path).is_ok_and(|metadata| metadata.len() > 0);
+        Self {
+            path,
+            lock: Mutex::new(()),
+            has_messages: AtomicBool::new(has_messages),
+        }
+    }
+
+    pub(crate) fn append(&self, message: &QuickwitLogMessage) -> io::Result<()> {
+        let _guard = self
+            .lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        ndjson::serialize(&mut writer, message)?;
+        writer.flush()?;
+        self.has_messages.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    pub(crate) fn has_messages(&self) -> bool {
+        self.has_messages.load(Ordering::Acquire)
+    }
+
+    // Reads every spilled message and empties the file. Lines that can't be parsed are skipped.
+    pub(crate) fn take(&self) -> io::Result<Vec<QuickwitLogMessage>> {
+        let _guard = self
+            .lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        fs::write(&self.path, b"")?;
+        self.has_messages.store(false, Ordering::Release);
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
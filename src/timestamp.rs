@@ -0,0 +1,44 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How the time an event was recorded at is written into its document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// E.g. `2024-11-30T12:34:56.789012345Z`.
+    #[default]
+    Rfc3339,
+    UnixSeconds,
+    UnixMillis,
+    UnixMicros,
+    UnixNanos,
+}
+
+impl TimestampFormat {
+    pub(crate) fn format(self, time: SystemTime) -> serde_json::Value {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        match self {
+            TimestampFormat::Rfc3339 => humantime::format_rfc3339_nanos(time).to_string().into(),
+            TimestampFormat::UnixSeconds => since_epoch.as_secs().into(),
+            TimestampFormat::UnixMillis => (since_epoch.as_millis() as u64).into(),
+            TimestampFormat::UnixMicros => (since_epoch.as_micros() as u64).into(),
+            TimestampFormat::UnixNanos => (since_epoch.as_nanos() as u64).into(),
+        }
+    }
+
+    // Names of the format in Quickwit's doc mapping.
+    pub(crate) fn input_format(self) -> &'static str {
+        match self {
+            TimestampFormat::Rfc3339 => "rfc3339",
+            _ => "unix_timestamp",
+        }
+    }
+
+    pub(crate) fn output_format(self) -> &'static str {
+        match self {
+            TimestampFormat::Rfc3339 => "rfc3339",
+            TimestampFormat::UnixSeconds => "unix_timestamp_secs",
+            TimestampFormat::UnixMillis => "unix_timestamp_millis",
+            TimestampFormat::UnixMicros => "unix_timestamp_micros",
+            TimestampFormat::UnixNanos => "unix_timestamp_nanos",
+        }
+    }
+}
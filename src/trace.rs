@@ -0,0 +1,173 @@
+use crate::defaults::DEFAULT_TRACES_INDEX_ID;
+use serde_json::json;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing_core::{Event, Metadata};
+
+// Crates sending the requests to Quickwit, whose spans would be exported while exporting spans.
+const HTTP_CLIENT_TARGETS: [&str; 5] = ["h2", "hyper", "hyper_util", "reqwest", "tower"];
+
+type SpanFilter = Arc<dyn Fn(&Metadata<'_>) -> bool + Send + Sync + 'static>;
+
+/// Sends spans, once closed, to Quickwit's OpenTelemetry traces index.
+///
+/// The spans of the HTTP client sending them (`hyper`, `h2`, `reqwest` etc.) are never exported.
+#[derive(Clone)]
+pub struct TraceExport {
+    pub(crate) index_id: String,
+    service_name: String,
+    span_filter: Option<SpanFilter>,
+}
+
+impl TraceExport {
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Self {
+            index_id: DEFAULT_TRACES_INDEX_ID.to_string(),
+            service_name: service_name.into(),
+            span_filter: None,
+        }
+    }
+
+    /// The traces index, `otel-traces-v0_7` by default.
+    pub fn with_index_id(mut self, index_id: impl Into<String>) -> Self {
+        self.index_id = index_id.into();
+        self
+    }
+
+    /// Only exports the spans `filter` returns `true` for, e.g. the ones of a target. Spans nested
+    /// in a filtered out span belong to the trace of the nearest exported span enclosing them.
+    pub fn with_span_filter(
+        mut self,
+        filter: impl Fn(&Metadata<'_>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.span_filter = Some(Arc::new(filter));
+        self
+    }
+
+    pub(crate) fn exports(&self, metadata: &Metadata<'_>) -> bool {
+        let target = metadata.target();
+        let is_http_client = HTTP_CLIENT_TARGETS.iter().any(|crate_name| {
+            target
+                .strip_prefix(crate_name)
+                .is_some_and(|path| path.is_empty() || path.starts_with("::"))
+        });
+        !is_http_client
+            && self
+                .span_filter
+                .as_ref()
+                .is_none_or(|span_filter| span_filter(metadata))
+    }
+}
+
+impl fmt::Debug for TraceExport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceExport")
+            .field("index_id", &self.index_id)
+            .field("service_name", &self.service_name)
+            .field("span_filter", &self.span_filter.is_some())
+            .finish()
+    }
+}
+
+// Stored in the extensions of every span while trace export is on.
+pub(crate) struct SpanRecord {
+    pub(crate) trace_id: String,
+    pub(crate) span_id: String,
+    parent_span_id: Option<String>,
+    started_at: SystemTime,
+    pub(crate) attributes: serde_json::Map<String, serde_json::Value>,
+    events: Vec<serde_json::Value>,
+    has_error: bool,
+}
+
+impl SpanRecord {
+    pub(crate) fn new(
+        parent: Option<&SpanRecord>,
+        attributes: serde_json::Map<String, serde_json::Value>,
+    ) -> Self {
+        Self {
+            trace_id: parent
+                .map(|parent| parent.trace_id.clone())
+                .unwrap_or_else(|| format!("{:032x}", fastrand::u128(1..))),
+            span_id: format!("{:016x}", fastrand::u64(1..)),
+            parent_span_id: parent.map(|parent| parent.span_id.clone()),
+            started_at: SystemTime::now(),
+            attributes,
+            events: Vec::new(),
+            has_error: false,
+        }
+    }
+
+    pub(crate) fn record_event(
+        &mut self,
+        event: &Event<'_>,
+        mut fields: serde_json::Map<String, serde_json::Value>,
+    ) {
+        let metadata = event.metadata();
+        self.has_error |= *metadata.level() == tracing_core::Level::ERROR;
+        let event_name = match fields.remove("message") {
+            Some(serde_json::Value::String(message)) => message,
+            _ => metadata.name().to_string(),
+        };
+        self.events.push(json!({
+            "event_timestamp_nanos": unix_nanos(SystemTime::now()),
+            "event_name": event_name,
+            "event_attributes": fields,
+        }));
+    }
+
+    // The document of the span in the shape of Quickwit's `otel-traces-v0_7` index.
+    pub(crate) fn into_document(
+        self,
+        metadata: &Metadata<'_>,
+        trace_export: &TraceExport,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        let ended_at = SystemTime::now();
+        let duration = ended_at.duration_since(self.started_at).unwrap_or_default();
+        let event_names = self
+            .events
+            .iter()
+            .map(|event| event["event_name"].clone())
+            .collect::<Vec<_>>();
+        let span_status = if self.has_error {
+            json!({"code": "error"})
+        } else {
+            json!({"code": "unset"})
+        };
+        let serde_json::Value::Object(document) = json!({
+            "trace_id": self.trace_id,
+            "span_id": self.span_id,
+            "parent_span_id": self.parent_span_id,
+            "is_root": self.parent_span_id.is_none(),
+            "service_name": trace_export.service_name,
+            "resource_attributes": {},
+            "scope_name": metadata.target(),
+            "span_kind": SPAN_KIND_INTERNAL,
+            "span_name": metadata.name(),
+            "span_start_timestamp_nanos": unix_nanos(self.started_at),
+            "span_end_timestamp_nanos": unix_nanos(ended_at),
+            "span_duration_millis": duration.as_millis() as u64,
+            "span_attributes": self.attributes,
+            "span_dropped_attributes_count": 0,
+            "span_dropped_events_count": 0,
+            "span_dropped_links_count": 0,
+            "span_status": span_status,
+            "events": self.events,
+            "event_names": event_names,
+            "links": [],
+        }) else {
+            unreachable!("Span document isn't an object!");
+        };
+        document
+    }
+}
+
+// See `SpanKind` in the OpenTelemetry protocol.
+const SPAN_KIND_INTERNAL: u64 = 1;
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fmt;
+
+/// Everything wrong with a `QuickwitLoggingLayerBuilder`, returned by `try_build`.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<ConfigProblem>,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigProblem {
+    /// `marker_field` wasn't set, so no event would ever be routed.
+    EmptyMarkerField,
+    /// `map_marker_to_index` wasn't called, so no event would ever be routed.
+    NoIndexMapped,
+    ZeroBatchSize,
+    ZeroChannelCapacity,
+    /// The Quickwit URL's scheme is neither `http` nor `https`.
+    UnsupportedScheme {
+        scheme: String,
+    },
+    /// The Quickwit URL can't have paths joined to it, e.g. `mailto:` or `data:` URLs.
+    InvalidUrl {
+        url: String,
+    },
+    /// Quickwit index ids must match `^[a-zA-Z][a-zA-Z0-9-_.]{2,254}$`.
+    InvalidIndexId {
+        index_id: String,
+    },
+    /// A header added with `with_header` has a name or a value that can't be sent, e.g. one
+    /// containing a line break.
+    InvalidHeader {
+        name: String,
+    },
+    /// Builder options ignored because `with_http_client` was called, named after their methods.
+    OverriddenByHttpClient {
+        options: Vec<&'static str>,
+    },
+    /// The HTTP client couldn't be built, e.g. because the CA bundle isn't valid PEM.
+    HttpClient {
+        source: Box<dyn Error + Send + Sync + 'static>,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid Quickwit logging layer configuration")?;
+        for (i, problem) in self.problems.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { "; " };
+            write!(f, "{}{}", separator, problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigError {}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigProblem::EmptyMarkerField => write!(f, "marker field is empty"),
+            ConfigProblem::NoIndexMapped => write!(f, "no marker value is mapped to an index"),
+            ConfigProblem::ZeroBatchSize => write!(f, "batch size is zero"),
+            ConfigProblem::ZeroChannelCapacity => write!(f, "channel capacity is zero"),
+            ConfigProblem::UnsupportedScheme { scheme } => {
+                write!(f, "URL scheme `{}` isn't `http` or `https`", scheme)
+            }
+            ConfigProblem::InvalidUrl { url } => {
+                write!(f, "`{}` can't be used as the Quickwit URL", url)
+            }
+            ConfigProblem::InvalidIndexId { index_id } => {
+                write!(f, "`{}` isn't a valid Quickwit index id", index_id)
+            }
+            ConfigProblem::InvalidHeader { name } => {
+                write!(f, "header `{}` has an invalid name or value", name)
+            }
+            ConfigProblem::OverriddenByHttpClient { options } => {
+                write!(
+                    f,
+                    "`{}` ignored in favor of the custom HTTP client",
+                    options.join("`, `")
+                )
+            }
+            ConfigProblem::HttpClient { source } => {
+                write!(f, "failed to build the HTTP client: {}", source)
+            }
+        }
+    }
+}
+
+impl ConfigProblem {
+    // Whether `build` panics on it too, since the layer couldn't work at all.
+    pub(crate) fn prevents_building(&self) -> bool {
+        matches!(
+            self,
+            ConfigProblem::ZeroChannelCapacity
+                | ConfigProblem::InvalidUrl { .. }
+                | ConfigProblem::InvalidHeader { .. }
+                | ConfigProblem::HttpClient { .. }
+        )
+    }
+}
+
+pub(crate) fn is_valid_index_id(index_id: &str) -> bool {
+    let mut chars = index_id.chars();
+    let starts_with_letter = chars.next().is_some_and(|c| c.is_ascii_alphabetic());
+    starts_with_letter
+        && (3..=255).contains(&index_id.len())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
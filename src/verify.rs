@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fmt;
+
+/// Why `QuickwitHandle::verify` considers Quickwit unfit to receive logs.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VerifyError {
+    /// A request didn't reach Quickwit or its response couldn't be read.
+    Unreachable {
+        url: String,
+        source: Box<dyn Error + Send + Sync + 'static>,
+    },
+    /// Quickwit answered with an unexpected status, e.g. `401` or `503`.
+    UnexpectedStatus {
+        url: String,
+        status: u16,
+        body: String,
+    },
+    /// Quickwit is up but some of the mapped indexes don't exist.
+    MissingIndexes { index_ids: Vec<String> },
+    /// The background task isn't running anymore.
+    Stopped,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Unreachable { url, source } => {
+                write!(f, "Quickwit is unreachable at `{}`: {}", url, source)
+            }
+            VerifyError::UnexpectedStatus { url, status, body } => {
+                write!(
+                    f,
+                    "Quickwit answered `{}` with status {}: {}",
+                    url, status, body
+                )
+            }
+            VerifyError::MissingIndexes { index_ids } => {
+                write!(f, "indexes don't exist: {}", index_ids.join(", "))
+            }
+            VerifyError::Stopped => write!(f, "background task isn't running"),
+        }
+    }
+}
+
+impl Error for VerifyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VerifyError::Unreachable { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
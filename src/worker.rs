@@ -0,0 +1,642 @@
+use crate::auth::RequestCredentials;
+use crate::commit::CommitMode;
+use crate::compression::Compression;
+use crate::document::DocumentConfig;
+use crate::dropped::{DropCounters, DropWindow};
+use crate::endpoint;
+use crate::error::{ErrorHook, QuickwitError};
+use crate::handle::{Command, FlushReport};
+use crate::index_config::IndexConfig;
+use crate::message::QuickwitLogMessage;
+use crate::protocol::IngestProtocol;
+use crate::queue::EventQueue;
+use crate::report::IngestReport;
+use crate::retry::RetryPolicy;
+use crate::spill::SpillFile;
+use crate::verify::VerifyError;
+use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tokio::time::{self, Instant};
+use tracing::instrument::WithSubscriber;
+use tracing::Dispatch;
+use url::Url;
+
+pub(crate) struct WorkerConfig {
+    pub(crate) http_client: Client,
+    pub(crate) request_timeout: Option<Duration>,
+    pub(crate) quickwit_url: Url,
+    pub(crate) batch_size: usize,
+    pub(crate) flush_interval: Option<Duration>,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) compression: Compression,
+    pub(crate) ingest_protocol: IngestProtocol,
+    pub(crate) commit_mode: CommitMode,
+    pub(crate) index_commit_modes: HashMap<String, CommitMode>,
+    pub(crate) index_configs: HashMap<String, IndexConfig>,
+    pub(crate) credentials: RequestCredentials,
+    pub(crate) on_error: ErrorHook,
+    pub(crate) spill_file: Option<Arc<SpillFile>>,
+    pub(crate) drop_counters: Arc<DropCounters>,
+    pub(crate) dropped_events_summary: bool,
+    pub(crate) document_config: DocumentConfig,
+    pub(crate) on_ingest_report: Arc<dyn Fn(&IngestReport) + Send + Sync + 'static>,
+}
+
+pub(crate) struct Worker {
+    config: WorkerConfig,
+    buffers: HashMap<String, Buffer>,
+    // Indexes known to exist, so that they aren't created before the next flush to them.
+    existing_indexes: HashSet<String>,
+}
+
+struct Buffer {
+    logs: Vec<serde_json::Map<String, serde_json::Value>>,
+    // When the oldest log currently in the buffer was pushed.
+    oldest_log_at: Option<Instant>,
+}
+
+struct Batch {
+    index_id: String,
+    logs_count: usize,
+    serialized_logs_count: usize,
+    // The serialized logs along with the summary of dropped events if there's one.
+    documents_count: usize,
+    unreported_drops: Option<DropWindow>,
+}
+
+// Why a request failed, before it's reported for every batch it carried.
+enum SendFailure {
+    Transport(Arc<reqwest::Error>),
+    HttpStatus { status: u16, body: String },
+    IndexUnknown,
+}
+
+impl SendFailure {
+    fn into_errors(self, batches: &[Batch], attempt: u32) -> Vec<QuickwitError> {
+        batches
+            .iter()
+            .map(|batch| {
+                let index_id = batch.index_id.clone();
+                let batch_size = batch.serialized_logs_count;
+                match &self {
+                    SendFailure::Transport(source) => QuickwitError::Transport {
+                        index_id,
+                        batch_size,
+                        attempt,
+                        source: Box::new(Arc::clone(source)),
+                    },
+                    SendFailure::HttpStatus { status, body } => QuickwitError::HttpStatus {
+                        index_id,
+                        batch_size,
+                        attempt,
+                        status: *status,
+                        body: body.clone(),
+                    },
+                    SendFailure::IndexUnknown => QuickwitError::IndexUnknown {
+                        index_id,
+                        batch_size,
+                        attempt,
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+impl Buffer {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            logs: Vec::with_capacity(capacity),
+            oldest_log_at: None,
+        }
+    }
+}
+
+impl Worker {
+    pub(crate) fn new(config: WorkerConfig, index_ids: impl IntoIterator<Item = String>) -> Self {
+        let buffers = index_ids
+            .into_iter()
+            .map(|index_id| (index_id, Buffer::with_capacity(config.batch_size)))
+            .collect();
+        Self {
+            config,
+            buffers,
+            existing_indexes: HashSet::new(),
+        }
+    }
+
+    pub(crate) async fn run(
+        mut self,
+        queue: Arc<EventQueue>,
+        mut commands: mpsc::UnboundedReceiver<Command>,
+    ) {
+        loop {
+            let deadline = self.next_flush_deadline();
+            tokio::select! {
+                message = queue.recv() => match message {
+                    Some(QuickwitLogMessage { index_id, log }) => {
+                        self.push(index_id, log).await;
+                        if queue.is_empty() {
+                            self.replay_spilled().await;
+                        }
+                    }
+                    None => break,
+                },
+                Some(command) = commands.recv() => match command {
+                    Command::Flush(reply) => {
+                        let mut report = self.drain(&queue).await;
+                        report += self.flush_all().await;
+                        reply.send(report).ok();
+                    }
+                    Command::Shutdown(reply) => {
+                        queue.close();
+                        let mut report = self.drain(&queue).await;
+                        report += self.flush_all().await;
+                        reply.send(report).ok();
+                        return;
+                    }
+                    Command::Verify(reply) => {
+                        reply.send(self.verify().await).ok();
+                    }
+                    Command::CreateIndexes(reply) => {
+                        reply.send(self.create_missing_indexes().await).ok();
+                    }
+                },
+                _ = sleep_until(deadline) => {
+                    self.flush_expired().await;
+                }
+            }
+        }
+        self.replay_spilled().await;
+        self.flush_all().await;
+    }
+
+    // Moves the messages that are already in the channel or in the spill file into the buffers.
+    async fn drain(&mut self, queue: &EventQueue) -> FlushReport {
+        let mut report = FlushReport::default();
+        while let Some(QuickwitLogMessage { index_id, log }) = queue.try_recv() {
+            report += self.push(index_id, log).await;
+        }
+        report += self.replay_spilled().await;
+        report
+    }
+
+    async fn replay_spilled(&mut self) -> FlushReport {
+        let mut report = FlushReport::default();
+        let Some(spill_file) = self.config.spill_file.clone() else {
+            return report;
+        };
+        if !spill_file.has_messages() {
+            return report;
+        }
+        match spill_file.take() {
+            Ok(messages) => {
+                for QuickwitLogMessage { index_id, log } in messages {
+                    report += self.push(index_id, log).await;
+                }
+            }
+            Err(err) => (self.config.on_error)(QuickwitError::Spill { source: err }),
+        }
+        report
+    }
+
+    async fn push(
+        &mut self,
+        index_id: String,
+        log: serde_json::Map<String, serde_json::Value>,
+    ) -> FlushReport {
+        let batch_size = self.config.batch_size;
+        let buffer = self
+            .buffers
+            .entry(index_id.clone())
+            .or_insert_with(|| Buffer::with_capacity(batch_size));
+        buffer.logs.push(log);
+        buffer.oldest_log_at.get_or_insert_with(Instant::now);
+        if buffer.logs.len() >= self.config.batch_size {
+            return self.flush(&index_id).await;
+        }
+        FlushReport::default()
+    }
+
+    fn next_flush_deadline(&self) -> Option<Instant> {
+        let flush_interval = self.config.flush_interval?;
+        self.buffers
+            .values()
+            .filter_map(|buffer| buffer.oldest_log_at)
+            .min()
+            .map(|oldest_log_at| oldest_log_at + flush_interval)
+    }
+
+    async fn flush_expired(&mut self) {
+        let Some(flush_interval) = self.config.flush_interval else {
+            return;
+        };
+        let now = Instant::now();
+        let expired = self
+            .buffers
+            .iter()
+            .filter(|(_, buffer)| {
+                buffer
+                    .oldest_log_at
+                    .is_some_and(|oldest_log_at| oldest_log_at + flush_interval <= now)
+            })
+            .map(|(index_id, _)| index_id.clone())
+            .collect::<Vec<_>>();
+        for index_id in expired {
+            self.flush(&index_id).await;
+        }
+    }
+
+    async fn flush_all(&mut self) -> FlushReport {
+        if self.config.ingest_protocol == IngestProtocol::ElasticBulk {
+            return self.flush_bulk().await;
+        }
+        let mut report = FlushReport::default();
+        let index_ids = self.buffers.keys().cloned().collect::<Vec<_>>();
+        for index_id in index_ids {
+            report += self.flush(&index_id).await;
+        }
+        report
+    }
+
+    async fn flush(&mut self, index_id: &str) -> FlushReport {
+        if self.config.ingest_protocol == IngestProtocol::ElasticBulk {
+            // The other buffers ride along since it costs no extra request.
+            return self.flush_bulk().await;
+        }
+        // TODO: Reuse `ndjson_body`.
+        let mut ndjson_body = Vec::new();
+        match self.take_batch(index_id, &mut ndjson_body) {
+            Some(batch) => self.send_batches(vec![batch], ndjson_body).await,
+            None => FlushReport::default(),
+        }
+    }
+
+    // Sends the documents of every index in a single request.
+    async fn flush_bulk(&mut self) -> FlushReport {
+        let mut ndjson_body = Vec::new();
+        let index_ids = self.buffers.keys().cloned().collect::<Vec<_>>();
+        let batches = index_ids
+            .iter()
+            .filter_map(|index_id| self.take_batch(index_id, &mut ndjson_body))
+            .collect::<Vec<_>>();
+        if batches.is_empty() {
+            return FlushReport::default();
+        }
+        self.send_batches(batches, ndjson_body).await
+    }
+
+    // Moves the logs of an index's buffer into `ndjson_body`, preceded by a summary of the
+    // events dropped since the last flush if there is one to report.
+    fn take_batch(&mut self, index_id: &str, ndjson_body: &mut Vec<u8>) -> Option<Batch> {
+        let buffer = self.buffers.get_mut(index_id)?;
+        buffer.oldest_log_at = None;
+        // Summaries don't fit the span documents of the traces index.
+        let is_traces_index = self
+            .config
+            .document_config
+            .trace_export
+            .as_ref()
+            .is_some_and(|trace_export| trace_export.index_id == index_id);
+        let unreported_drops = if self.config.dropped_events_summary && !is_traces_index {
+            self.config.drop_counters.take_unreported(index_id)
+        } else {
+            None
+        };
+        if buffer.logs.is_empty() && unreported_drops.is_none() {
+            return None;
+        }
+        let protocol = self.config.ingest_protocol;
+        let mut documents_count = 0;
+        if let Some(unreported_drops) = &unreported_drops {
+            let mut summary = unreported_drops.to_summary_document();
+            self.config
+                .document_config
+                .insert_timestamp(&mut summary, SystemTime::now());
+            if protocol
+                .serialize_document(ndjson_body, index_id, &summary)
+                .is_ok()
+            {
+                documents_count += 1;
+            }
+        }
+        let logs_count = buffer.logs.len();
+        let mut serialized_logs_count = 0;
+        for log in buffer.logs.drain(..) {
+            match protocol.serialize_document(ndjson_body, index_id, &log) {
+                Ok(()) => serialized_logs_count += 1,
+                Err(err) => (self.config.on_error)(QuickwitError::Serialization {
+                    index_id: index_id.to_string(),
+                    source: err.into(),
+                }),
+            }
+        }
+        Some(Batch {
+            index_id: index_id.to_string(),
+            logs_count,
+            serialized_logs_count,
+            documents_count: documents_count + serialized_logs_count,
+            unreported_drops,
+        })
+    }
+
+    async fn send_batches(&mut self, batches: Vec<Batch>, ndjson_body: Vec<u8>) -> FlushReport {
+        for batch in &batches {
+            // Sending the batch anyway reports the failure as the index being unknown.
+            if let Err(err) = self.create_index_if_missing(&batch.index_id).await {
+                (self.config.on_error)(err);
+            }
+        }
+        let logs_count = batches.iter().map(|batch| batch.logs_count).sum();
+        let serialized_logs_count = batches
+            .iter()
+            .map(|batch| batch.serialized_logs_count)
+            .sum();
+        // Requests to the bulk endpoint are committed the strictest way any of their indexes is.
+        let commit_mode = batches
+            .iter()
+            .map(|batch| self.commit_mode(&batch.index_id))
+            .max()
+            .unwrap_or_default();
+        match self.send(&batches, commit_mode, ndjson_body).await {
+            Ok(response_body) => {
+                let batch_sizes = batches
+                    .iter()
+                    .map(|batch| (batch.index_id.clone(), batch.documents_count))
+                    .collect::<HashMap<_, _>>();
+                // Responses of unexpected shape aren't worth failing the batch over.
+                let (reports, rejections) = self
+                    .config
+                    .ingest_protocol
+                    .parse_response(&response_body, &batch_sizes);
+                for report in &reports {
+                    (self.config.on_ingest_report)(report);
+                }
+                let rejected_logs_count = reports
+                    .iter()
+                    .map(|report| report.rejected() as usize)
+                    .sum::<usize>()
+                    .min(serialized_logs_count);
+                for rejection in rejections {
+                    (self.config.on_error)(rejection);
+                }
+                FlushReport {
+                    delivered: serialized_logs_count - rejected_logs_count,
+                    lost: logs_count - serialized_logs_count + rejected_logs_count,
+                }
+            }
+            Err(errors) => {
+                for err in errors {
+                    (self.config.on_error)(err);
+                }
+                for batch in batches {
+                    if let Some(unreported_drops) = batch.unreported_drops {
+                        self.config
+                            .drop_counters
+                            .restore_unreported(&batch.index_id, unreported_drops);
+                    }
+                }
+                FlushReport {
+                    delivered: 0,
+                    lost: logs_count,
+                }
+            }
+        }
+    }
+
+    fn commit_mode(&self, index_id: &str) -> CommitMode {
+        self.config
+            .index_commit_modes
+            .get(index_id)
+            .copied()
+            .unwrap_or(self.config.commit_mode)
+    }
+
+    // A failed request is reported once per batch it carried.
+    async fn send(
+        &self,
+        batches: &[Batch],
+        commit_mode: CommitMode,
+        ndjson_body: Vec<u8>,
+    ) -> Result<String, Vec<QuickwitError>> {
+        // Bulk requests don't name an index in their URL, the others carry a single batch.
+        let url = self.config.ingest_protocol.endpoint(
+            &self.config.quickwit_url,
+            &batches[0].index_id,
+            commit_mode,
+        );
+        let body = match self.config.compression.compress(ndjson_body) {
+            Ok(body) => body,
+            Err(err) => {
+                let err = Arc::new(err);
+                let errors = batches
+                    .iter()
+                    .map(|batch| QuickwitError::Serialization {
+                        index_id: batch.index_id.clone(),
+                        source: Box::new(Arc::clone(&err)),
+                    })
+                    .collect();
+                return Err(errors);
+            }
+        };
+        let mut attempt = 0;
+        let mut token_refreshed = false;
+        loop {
+            attempt += 1;
+            let response = send_untraced(self.ingest_request(url.clone(), body.clone())).await;
+            let (failure, retryable) = match response {
+                Ok(response) if response.status().is_success() => {
+                    return Ok(response.text().await.unwrap_or_default());
+                }
+                Ok(response) if response.status() == StatusCode::NOT_FOUND => {
+                    (SendFailure::IndexUnknown, false)
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    if status == StatusCode::UNAUTHORIZED
+                        && self.config.credentials.invalidate()
+                        && !token_refreshed
+                    {
+                        // Resent right away with a fresh token, whatever the retry policy.
+                        token_refreshed = true;
+                        continue;
+                    }
+                    let retryable = self.config.retry_policy.is_retryable(status);
+                    let body = response.text().await.unwrap_or_default();
+                    let failure = SendFailure::HttpStatus {
+                        status: status.as_u16(),
+                        body,
+                    };
+                    (failure, retryable)
+                }
+                Err(err) => {
+                    let retryable = !err.is_builder();
+                    (SendFailure::Transport(Arc::new(err)), retryable)
+                }
+            };
+            if !retryable || attempt >= self.config.retry_policy.max_attempts() {
+                return Err(failure.into_errors(batches, attempt));
+            }
+            time::sleep(self.config.retry_policy.backoff(attempt)).await;
+        }
+    }
+
+    async fn create_missing_indexes(&mut self) -> Result<Vec<String>, QuickwitError> {
+        let mut index_ids = self
+            .config
+            .index_configs
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        index_ids.sort();
+        let mut created_index_ids = Vec::new();
+        for index_id in index_ids {
+            if self.create_index_if_missing(&index_id).await? {
+                created_index_ids.push(index_id);
+            }
+        }
+        Ok(created_index_ids)
+    }
+
+    // Returns whether the index was created.
+    async fn create_index_if_missing(&mut self, index_id: &str) -> Result<bool, QuickwitError> {
+        if self.existing_indexes.contains(index_id) {
+            return Ok(false);
+        }
+        let Some(index_config) = self.config.index_configs.get(index_id) else {
+            return Ok(false);
+        };
+        let index_creation_error = |reason: String| QuickwitError::IndexCreation {
+            index_id: index_id.to_string(),
+            reason,
+        };
+        let index_url = endpoint::join(
+            &self.config.quickwit_url,
+            &["api", "v1", "indexes", index_id],
+        );
+        let response = send_untraced(self.request(Method::GET, index_url))
+            .await
+            .map_err(|err| index_creation_error(err.to_string()))?;
+        match response.status() {
+            status if status.is_success() => {
+                self.existing_indexes.insert(index_id.to_string());
+                return Ok(false);
+            }
+            StatusCode::NOT_FOUND => {}
+            status => {
+                let body = response.text().await.unwrap_or_default();
+                let reason = format!(
+                    "Quickwit answered the index lookup with status {}: {}",
+                    status.as_u16(),
+                    body
+                );
+                return Err(index_creation_error(reason));
+            }
+        }
+        let indexes_url = endpoint::join(&self.config.quickwit_url, &["api", "v1", "indexes"]);
+        let request = self
+            .request(Method::POST, indexes_url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(index_config.to_json(index_id).to_string());
+        let response = send_untraced(request)
+            .await
+            .map_err(|err| index_creation_error(err.to_string()))?;
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        // Another process may have created it in the meantime.
+        let created = status.is_success();
+        if !created && !body.contains("already exists") {
+            let reason = format!(
+                "Quickwit answered with status {}: {}",
+                status.as_u16(),
+                body
+            );
+            return Err(index_creation_error(reason));
+        }
+        self.existing_indexes.insert(index_id.to_string());
+        Ok(created)
+    }
+
+    async fn verify(&self) -> Result<(), VerifyError> {
+        let health_url = endpoint::join(&self.config.quickwit_url, &["health", "livez"]);
+        let response = self.get(health_url.clone()).await?;
+        if !response.status().is_success() {
+            return Err(unexpected_status(health_url, response).await);
+        }
+        let mut index_ids = self.buffers.keys().collect::<Vec<_>>();
+        index_ids.sort();
+        let mut missing_index_ids = Vec::new();
+        for index_id in index_ids {
+            let index_url = endpoint::join(
+                &self.config.quickwit_url,
+                &["api", "v1", "indexes", index_id],
+            );
+            let response = self.get(index_url.clone()).await?;
+            match response.status() {
+                status if status.is_success() => {}
+                StatusCode::NOT_FOUND => missing_index_ids.push(index_id.clone()),
+                _ => return Err(unexpected_status(index_url, response).await),
+            }
+        }
+        if !missing_index_ids.is_empty() {
+            return Err(VerifyError::MissingIndexes {
+                index_ids: missing_index_ids,
+            });
+        }
+        Ok(())
+    }
+
+    async fn get(&self, url: Url) -> Result<Response, VerifyError> {
+        send_untraced(self.request(Method::GET, url.clone()))
+            .await
+            .map_err(|err| VerifyError::Unreachable {
+                url: url.to_string(),
+                source: Box::new(err),
+            })
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let mut request = self
+            .config
+            .credentials
+            .apply(self.config.http_client.request(method, url));
+        if let Some(request_timeout) = self.config.request_timeout {
+            request = request.timeout(request_timeout);
+        }
+        request
+    }
+
+    fn ingest_request(&self, url: Url, body: Vec<u8>) -> RequestBuilder {
+        let mut request = self.request(Method::POST, url).body(body);
+        if let Some(content_encoding) = self.config.compression.content_encoding() {
+            request = request.header(CONTENT_ENCODING, content_encoding);
+        }
+        request
+    }
+}
+
+// Keeps the spans the HTTP client creates while sending from reaching the layer, which would
+// export them and send more requests.
+async fn send_untraced(request: RequestBuilder) -> reqwest::Result<Response> {
+    request.send().with_subscriber(Dispatch::none()).await
+}
+
+async fn unexpected_status(url: Url, response: Response) -> VerifyError {
+    VerifyError::UnexpectedStatus {
+        url: url.to_string(),
+        status: response.status().as_u16(),
+        body: response.text().await.unwrap_or_default(),
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => time::sleep_until(deadline).await,
+        None => future::pending().await,
+    }
+}
@@ -0,0 +1,95 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing_quickwit::QuickwitLoggingLayerBuilder;
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+async fn send_logs(
+    builder: QuickwitLoggingLayerBuilder,
+    quickwit_server: &TestHttpServer,
+    events_count: u64,
+) {
+    quickwit_server.wait_until_ready().await;
+    let (layer, handle, background_task) = builder
+        .marker_field("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .with_batch_size(1)
+        .build();
+    tokio::spawn(background_task);
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        for number in 0..events_count {
+            tracing::info!(task = "billing", number);
+        }
+    });
+    handle.flush().await;
+}
+
+#[tokio::test]
+async fn send_static_headers_and_basic_auth() {
+    let quickwit_server = TestHttpServer::new(9036, 1, Vec::new());
+    let builder = QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9036").unwrap())
+        .with_header("X-Tenant", "billing-team")
+        .with_basic_auth("quickwit", Some("secret"));
+    send_logs(builder, &quickwit_server, 1).await;
+
+    let requests = quickwit_server.received_requests();
+    assert_eq!(requests.len(), 1);
+    let headers = &requests[0].headers;
+    assert_eq!(
+        headers.get("x-tenant").map(String::as_str),
+        Some("billing-team")
+    );
+    assert_eq!(
+        headers.get("authorization").map(String::as_str),
+        Some("Basic cXVpY2t3aXQ6c2VjcmV0"),
+    );
+}
+
+#[tokio::test]
+async fn refresh_token_from_provider() {
+    let quickwit_server = TestHttpServer::new(9037, 2, Vec::new());
+    let calls = AtomicUsize::new(0);
+    let builder = QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9037").unwrap())
+        .with_token_provider(
+            move || format!("token-{}", calls.fetch_add(1, Ordering::SeqCst) + 1),
+            Duration::ZERO,
+        );
+    send_logs(builder, &quickwit_server, 2).await;
+
+    let authorization_headers = quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.headers["authorization"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        authorization_headers,
+        vec!["Bearer token-1".to_string(), "Bearer token-2".to_string()],
+    );
+}
+
+#[tokio::test]
+async fn resend_batch_with_fresh_token_after_unauthorized() {
+    let quickwit_server = TestHttpServer::new(9064, 1, vec![401]);
+    let calls = AtomicUsize::new(0);
+    let builder = QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9064").unwrap())
+        .with_token_provider(
+            move || format!("token-{}", calls.fetch_add(1, Ordering::SeqCst) + 1),
+            Duration::from_secs(60),
+        );
+    send_logs(builder, &quickwit_server, 1).await;
+
+    let authorization_headers = quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.headers["authorization"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        authorization_headers,
+        vec!["Bearer token-1".to_string(), "Bearer token-2".to_string()],
+    );
+    assert_eq!(quickwit_server.accepted_requests().len(), 1);
+}
@@ -0,0 +1,172 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use serde_json::json;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing_quickwit::{BackpressurePolicy, DropReason, QuickwitLoggingLayerBuilder};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+#[tokio::test]
+async fn drop_oldest_events_when_channel_is_full() {
+    let quickwit_server = TestHttpServer::new(9031, 2, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let dropped_clone = Arc::clone(&dropped);
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9031").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_channel_capacity(2)
+            .with_backpressure_policy(BackpressurePolicy::DropOldest)
+            .on_event_dropped(move |event| dropped_clone.lock().unwrap().push(event.clone()))
+            .build();
+
+    // The background task isn't running yet, so the channel can't be drained.
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        for number in 0..4_u64 {
+            tracing::info!(task = "billing", number);
+        }
+    });
+    background_task.await;
+
+    let dropped = dropped.lock().unwrap();
+    assert_eq!(
+        dropped
+            .iter()
+            .map(|event| (event.index_id.as_str(), event.reason, event.total_dropped))
+            .collect::<Vec<_>>(),
+        vec![
+            ("billing_logs", DropReason::EvictedByNewer, 1),
+            ("billing_logs", DropReason::EvictedByNewer, 2),
+        ],
+    );
+    assert_eq!(
+        quickwit_server.accepted_requests_without_timestamps(),
+        vec![
+            json!({"task": "billing", "number": 2}),
+            json!({"task": "billing", "number": 3}),
+        ],
+    );
+}
+
+#[tokio::test]
+async fn spill_events_to_disk_when_channel_is_full() {
+    let quickwit_server = TestHttpServer::new(9032, 3, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let spill_path = std::env::temp_dir().join(format!(
+        "tracing_quickwit_spill_{}.ndjson",
+        std::process::id(),
+    ));
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let dropped_clone = Arc::clone(&dropped);
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9032").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_channel_capacity(1)
+            .with_backpressure_policy(BackpressurePolicy::SpillToDisk {
+                path: spill_path.clone(),
+            })
+            .on_event_dropped(move |event| dropped_clone.lock().unwrap().push(event.clone()))
+            .build();
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        for number in 0..3_u64 {
+            tracing::info!(task = "billing", number);
+        }
+    });
+    assert_eq!(fs::read_to_string(&spill_path).unwrap().lines().count(), 2);
+    background_task.await;
+
+    assert!(dropped.lock().unwrap().is_empty());
+    assert_eq!(
+        quickwit_server.accepted_requests_without_timestamps(),
+        vec![
+            json!({"task": "billing", "number": 0}),
+            json!({"task": "billing", "number": 1}),
+            json!({"task": "billing", "number": 2}),
+        ],
+    );
+    assert_eq!(fs::read_to_string(&spill_path).unwrap(), "");
+    fs::remove_file(&spill_path).ok();
+}
+
+#[tokio::test]
+async fn drop_events_after_blocking_timeout() {
+    let quickwit_server = TestHttpServer::new(9058, 1, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let dropped_clone = Arc::clone(&dropped);
+    let timeout = Duration::from_millis(50);
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9058").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_channel_capacity(1)
+            .with_backpressure_policy(BackpressurePolicy::Block { timeout })
+            .on_event_dropped(move |event| dropped_clone.lock().unwrap().push(event.clone()))
+            .build();
+
+    // The background task isn't running yet, so the channel can't be drained.
+    let started_at = Instant::now();
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        for number in 0..2_u64 {
+            tracing::info!(task = "billing", number);
+        }
+    });
+    assert!(started_at.elapsed() >= timeout);
+    background_task.await;
+
+    let dropped = dropped.lock().unwrap();
+    assert_eq!(
+        dropped
+            .iter()
+            .map(|event| (event.index_id.as_str(), event.reason, event.total_dropped))
+            .collect::<Vec<_>>(),
+        vec![("billing_logs", DropReason::BlockTimedOut, 1)],
+    );
+    assert_eq!(
+        quickwit_server.accepted_requests_without_timestamps(),
+        vec![json!({"task": "billing", "number": 0})],
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn block_until_background_task_makes_room() {
+    let quickwit_server = TestHttpServer::new(9059, 3, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let dropped_clone = Arc::clone(&dropped);
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9059").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_channel_capacity(1)
+            .with_backpressure_policy(BackpressurePolicy::Block {
+                timeout: Duration::from_secs(10),
+            })
+            .on_event_dropped(move |event| dropped_clone.lock().unwrap().push(event.clone()))
+            .build();
+    let background_task = tokio::spawn(background_task);
+
+    // Blocks this worker thread while the other one drains the channel.
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        for number in 0..3_u64 {
+            tracing::info!(task = "billing", number);
+        }
+    });
+    background_task.await.unwrap();
+
+    assert!(dropped.lock().unwrap().is_empty());
+    assert_eq!(
+        quickwit_server.accepted_requests_without_timestamps(),
+        vec![
+            json!({"task": "billing", "number": 0}),
+            json!({"task": "billing", "number": 1}),
+            json!({"task": "billing", "number": 2}),
+        ],
+    );
+}
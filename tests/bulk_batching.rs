@@ -0,0 +1,58 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+use std::sync::{Arc, Mutex};
+use tracing_quickwit::{FlushReport, IngestProtocol, QuickwitError};
+
+#[tokio::test]
+async fn coalesce_indexes_into_one_bulk_request() {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_clone = Arc::clone(&reports);
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors_clone = Arc::clone(&errors);
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(10)
+        .with_quickwit_port(9041)
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("first_marker", "first_index")
+        .with_marker_to_index_mapping("second_marker", "second_index")
+        .with_ingest_protocol(IngestProtocol::ElasticBulk)
+        .on_ingest_report(move |report| reports_clone.lock().unwrap().push(report.clone()))
+        .on_error(move |err| errors_clone.lock().unwrap().push(err))
+        .build()
+        .await;
+
+    tracing::info!(some_marker_field = "first_marker", metric = "first");
+    tracing::info!(some_marker_field = "second_marker", metric = "second");
+    tracing::info!(
+        some_marker_field = "second_marker",
+        rejected_by_doc_mapping = true
+    );
+    let flush_report = env.quickwit_handle.flush().await;
+
+    assert_eq!(env.quickwit_server.received_requests_count(), 1);
+    assert_eq!(
+        flush_report,
+        FlushReport {
+            delivered: 2,
+            lost: 1
+        }
+    );
+    assert_eq!(env.quickwit_server.accepted_requests().len(), 2);
+    let mut reports = reports.lock().unwrap().clone();
+    reports.sort_by(|left, right| left.index_id.cmp(&right.index_id));
+    let counts = reports
+        .iter()
+        .map(|report| {
+            let index_id = report.index_id.as_str();
+            (index_id, report.accepted(), report.rejected())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![("first_index", 1, 0), ("second_index", 1, 1)]);
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        &errors[0],
+        QuickwitError::DocumentRejected { index_id, status: 400, .. } if index_id == "second_index"
+    ));
+}
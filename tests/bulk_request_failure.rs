@@ -0,0 +1,49 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+use std::sync::{Arc, Mutex};
+use tracing_quickwit::{FlushReport, IngestProtocol, QuickwitError, RetryPolicy};
+
+#[tokio::test]
+async fn report_failed_bulk_request_for_every_index() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors_clone = Arc::clone(&errors);
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(10)
+        .with_quickwit_port(9056)
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("first_marker", "first_index")
+        .with_marker_to_index_mapping("second_marker", "second_index")
+        .with_ingest_protocol(IngestProtocol::ElasticBulk)
+        .with_retry_policy(RetryPolicy::never())
+        .with_quickwit_canned_statuses(vec![500])
+        .on_error(move |err| errors_clone.lock().unwrap().push(err))
+        .build()
+        .await;
+
+    tracing::info!(some_marker_field = "first_marker", metric = "first");
+    tracing::info!(some_marker_field = "second_marker", metric = "second");
+    tracing::info!(some_marker_field = "second_marker", metric = "third");
+    let flush_report = env.quickwit_handle.flush().await;
+
+    assert_eq!(
+        flush_report,
+        FlushReport {
+            delivered: 0,
+            lost: 3
+        }
+    );
+    let errors = errors.lock().unwrap();
+    let mut failed_batches = errors
+        .iter()
+        .map(|err| {
+            assert!(matches!(err, QuickwitError::HttpStatus { status: 500, .. }));
+            (err.index_id().unwrap(), err.batch_size().unwrap())
+        })
+        .collect::<Vec<_>>();
+    failed_batches.sort();
+    assert_eq!(
+        failed_batches,
+        vec![("first_index", 1), ("second_index", 2)]
+    );
+}
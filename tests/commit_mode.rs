@@ -0,0 +1,30 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+use tracing_quickwit::CommitMode;
+
+#[tokio::test]
+async fn wait_for_commit() {
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(1)
+        .with_quickwit_port(9039)
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("marker_field_value", "some_index_id")
+        .with_commit_mode(CommitMode::WaitFor)
+        .build()
+        .await;
+
+    tracing::info!(some_marker_field = "marker_field_value", metric = "done");
+    env.quickwit_handle.flush().await;
+
+    let paths = env
+        .quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.path_and_query)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec!["/api/v1/some_index_id/ingest?commit=wait_for".to_string()],
+    );
+}
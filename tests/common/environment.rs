@@ -2,14 +2,19 @@ use crate::common::quickwit::TestHttpServer;
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Notify;
-use tracing_quickwit::QuickwitLoggingLayerBuilder;
+use tracing_quickwit::{
+    CommitMode, IngestProtocol, IngestReport, QuickwitError, QuickwitHandle,
+    QuickwitLoggingLayerBuilder, RetryPolicy,
+};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use url::Url;
 
 pub struct TestEnvironment {
     pub quickwit_server: TestHttpServer,
+    pub quickwit_handle: QuickwitHandle,
     all_emitted: Arc<Notify>,
 }
 
@@ -17,13 +22,20 @@ impl TestEnvironment {
     pub fn builder() -> TestEnvironmentBuilder<'static> {
         TestEnvironmentBuilder {
             quickwit_subscriber_channel_capacity: 1,
+            flush_interval: None,
+            retry_policy: None,
+            commit_mode: None,
+            ingest_protocol: None,
+            timestamp: true,
+            quickwit_canned_statuses: Vec::new(),
             expected_events_count: 0,
             emitted_events_count: 0,
             quickwit_port: 9011,
+            quickwit_path_prefix: "",
             marker_field: "task",
             marker_to_index_mapping: HashMap::new(),
-            on_index_missing: Box::new(|| ()),
-            on_ingest_failed: Box::new(|_err| ()),
+            on_error: Box::new(|_err| ()),
+            on_ingest_report: Box::new(|_report| ()),
         }
     }
 
@@ -34,11 +46,18 @@ impl TestEnvironment {
 
 pub struct TestEnvironmentBuilder<'mf> {
     quickwit_subscriber_channel_capacity: usize,
-    on_index_missing: Box<dyn Fn() + Send + Sync + 'static>,
-    on_ingest_failed: Box<dyn Fn(reqwest::Error) + Send + Sync + 'static>,
+    flush_interval: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+    commit_mode: Option<CommitMode>,
+    ingest_protocol: Option<IngestProtocol>,
+    timestamp: bool,
+    quickwit_canned_statuses: Vec<u16>,
+    on_error: Box<dyn Fn(QuickwitError) + Send + Sync + 'static>,
+    on_ingest_report: Box<dyn Fn(&IngestReport) + Send + Sync + 'static>,
     expected_events_count: usize,
     emitted_events_count: usize,
     quickwit_port: u16,
+    quickwit_path_prefix: &'mf str,
     marker_field: &'mf str,
     marker_to_index_mapping: HashMap<&'mf str, &'mf str>,
 }
@@ -49,16 +68,46 @@ impl<'mf> TestEnvironmentBuilder<'mf> {
         self
     }
 
-    pub fn on_index_missing(mut self, callback: impl Fn() + Send + Sync + 'static) -> Self {
-        self.on_index_missing = Box::new(callback);
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = Some(flush_interval);
         self
     }
 
-    pub fn on_ingest_failed(
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    pub fn with_commit_mode(mut self, commit_mode: CommitMode) -> Self {
+        self.commit_mode = Some(commit_mode);
+        self
+    }
+
+    pub fn with_ingest_protocol(mut self, ingest_protocol: IngestProtocol) -> Self {
+        self.ingest_protocol = Some(ingest_protocol);
+        self
+    }
+
+    pub fn without_timestamp(mut self) -> Self {
+        self.timestamp = false;
+        self
+    }
+
+    pub fn with_quickwit_canned_statuses(mut self, statuses: Vec<u16>) -> Self {
+        self.quickwit_canned_statuses = statuses;
+        self
+    }
+
+    pub fn on_error(mut self, callback: impl Fn(QuickwitError) + Send + Sync + 'static) -> Self {
+        self.on_error = Box::new(callback);
+        self
+    }
+
+    pub fn on_ingest_report(
         mut self,
-        callback: impl Fn(reqwest::Error) + Send + Sync + 'static,
+        callback: impl Fn(&IngestReport) + Send + Sync + 'static,
     ) -> Self {
-        self.on_ingest_failed = Box::new(callback);
+        self.on_ingest_report = Box::new(callback);
         self
     }
 
@@ -77,6 +126,12 @@ impl<'mf> TestEnvironmentBuilder<'mf> {
         self
     }
 
+    /// Makes the layer send requests to e.g. `http://127.0.0.1:<port>/quickwit`.
+    pub fn with_quickwit_path_prefix(mut self, path_prefix: &'mf str) -> Self {
+        self.quickwit_path_prefix = path_prefix;
+        self
+    }
+
     pub fn with_marker_field(mut self, field_name: &'mf str) -> Self {
         self.marker_field = field_name;
         self
@@ -97,20 +152,36 @@ impl<'mf> TestEnvironmentBuilder<'mf> {
         quickwit_url
             .set_port(Some(self.quickwit_port))
             .expect("Failed to set the port for Quickwit URL!");
+        quickwit_url.set_path(self.quickwit_path_prefix);
         let all_emitted = Arc::new(Notify::new());
         let all_emitted_clone = Arc::clone(&all_emitted);
         let mut quickqit_layer_builder = QuickwitLoggingLayerBuilder::new(quickwit_url)
             .marker_field(self.marker_field)
             .with_batch_size(self.quickwit_subscriber_channel_capacity)
             .with_expected_emitted_events_count(self.emitted_events_count)
-            .on_index_missing(self.on_index_missing)
-            .on_ingest_failed(self.on_ingest_failed)
+            .on_error(self.on_error)
+            .on_ingest_report(self.on_ingest_report)
             .on_emitted_all(all_emitted_clone);
         for (marker_name, index_name) in self.marker_to_index_mapping {
             quickqit_layer_builder =
                 quickqit_layer_builder.map_marker_to_index(marker_name, index_name);
         }
-        let (quickwit_logging_layer, quickwit_background_client_task) =
+        if let Some(flush_interval) = self.flush_interval {
+            quickqit_layer_builder = quickqit_layer_builder.with_flush_interval(flush_interval);
+        }
+        if let Some(commit_mode) = self.commit_mode {
+            quickqit_layer_builder = quickqit_layer_builder.with_commit_mode(commit_mode);
+        }
+        if let Some(ingest_protocol) = self.ingest_protocol {
+            quickqit_layer_builder = quickqit_layer_builder.with_ingest_protocol(ingest_protocol);
+        }
+        if !self.timestamp {
+            quickqit_layer_builder = quickqit_layer_builder.without_timestamp();
+        }
+        if let Some(retry_policy) = self.retry_policy {
+            quickqit_layer_builder = quickqit_layer_builder.with_retry_policy(retry_policy);
+        }
+        let (quickwit_logging_layer, quickwit_handle, quickwit_background_client_task) =
             quickqit_layer_builder.build();
 
         let background_task_ready = Arc::new(Notify::new());
@@ -127,11 +198,16 @@ impl<'mf> TestEnvironmentBuilder<'mf> {
             .try_init()
             .ok();
 
-        let quickwit_server = TestHttpServer::new(self.quickwit_port, self.expected_events_count);
+        let quickwit_server = TestHttpServer::new(
+            self.quickwit_port,
+            self.expected_events_count,
+            self.quickwit_canned_statuses,
+        );
         quickwit_server.wait_until_ready().await;
 
         TestEnvironment {
             quickwit_server,
+            quickwit_handle,
             all_emitted,
         }
     }
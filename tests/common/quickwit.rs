@@ -1,46 +1,63 @@
+use flate2::read::GzDecoder;
 use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Request, Response, Server};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::json;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Read;
 use std::sync::{Arc, Mutex};
 use tokio::sync::{oneshot, Notify};
 
+/// Events having this field are rejected the way Quickwit rejects documents not matching the
+/// index's doc mapping.
+pub const REJECTED_FIELD: &str = "rejected_by_doc_mapping";
+
+/// Everything but the body of a request the server received.
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: String,
+    pub path_and_query: String,
+    pub headers: HashMap<String, String>,
+}
+
 #[derive(Debug, Default)]
 pub struct TestHttpServer {
-    events: Arc<Mutex<Vec<String>>>,
+    state: Arc<ServerState>,
     ready: Arc<Notify>,
-    processed_all: Arc<Notify>,
     shutdown_trigger: Option<oneshot::Sender<()>>,
 }
 
+#[derive(Debug, Default)]
+struct ServerState {
+    events: Mutex<Vec<String>>,
+    received_requests: Mutex<Vec<ReceivedRequest>>,
+    canned_statuses: Mutex<VecDeque<u16>>,
+    missing_indexes: Mutex<HashSet<String>>,
+    created_indexes: Mutex<Vec<serde_json::Value>>,
+    expected_events_count: usize,
+    processed_all: Notify,
+}
+
 impl TestHttpServer {
-    pub fn new(port: u16, expected_events_count: usize) -> Self {
-        let requests = Arc::new(Mutex::new(Vec::new()));
-        let requests_clone = Arc::clone(&requests);
+    /// Answers the first requests with `canned_statuses` (in order) and the rest with `200 OK`.
+    /// Events are only recorded from the requests answered with a successful status.
+    pub fn new(port: u16, expected_events_count: usize, canned_statuses: Vec<u16>) -> Self {
+        let state = Arc::new(ServerState {
+            canned_statuses: Mutex::new(VecDeque::from(canned_statuses)),
+            expected_events_count,
+            ..ServerState::default()
+        });
+        let state_clone = Arc::clone(&state);
         let ready = Arc::new(Notify::new());
         let ready_clone = Arc::clone(&ready);
-        let processed_all = Arc::new(Notify::new());
-        let processed_all_clone = Arc::clone(&processed_all);
         let (shutdown_trigger, shutdown_listener) = oneshot::channel();
 
         tokio::spawn(async move {
             let service = make_service_fn(|_connection| {
-                let requests = Arc::clone(&requests_clone);
-                let processed_all = Arc::clone(&processed_all_clone);
-                let mut processed_requests = 0_usize;
+                let state = Arc::clone(&state_clone);
                 async move {
                     Ok::<_, hyper::Error>(service_fn(move |request: Request<Body>| {
-                        let requests = Arc::clone(&requests);
-                        let processed_all = Arc::clone(&processed_all);
-                        async move {
-                            let body_bytes = hyper::body::to_bytes(request.into_body()).await?;
-                            for raw_event in String::from_utf8_lossy(&body_bytes).lines() {
-                                requests.lock().unwrap().push(raw_event.to_string());
-                                processed_requests += 1;
-                            }
-                            if processed_requests >= expected_events_count {
-                                processed_all.notify_one();
-                            }
-                            Ok::<_, hyper::Error>(Response::new(Body::from("OK")))
-                        }
+                        let state = Arc::clone(&state);
+                        async move { state.handle(request).await }
                     }))
                 }
             });
@@ -59,10 +76,9 @@ impl TestHttpServer {
         });
 
         Self {
-            events: requests,
-            shutdown_trigger: Some(shutdown_trigger),
+            state,
             ready,
-            processed_all,
+            shutdown_trigger: Some(shutdown_trigger),
         }
     }
 
@@ -72,13 +88,40 @@ impl TestHttpServer {
 
     pub async fn wait_until_processed_expected_events_count(&self) {
         // TODO: Add a timeout because in some errorneous cases it will never return.
-        self.processed_all.notified().await;
+        self.state.processed_all.notified().await;
+    }
+
+    /// Makes index metadata and ingest requests for `index_id` answer `404 Not Found`.
+    pub fn mark_index_missing(&self, index_id: &str) {
+        self.state
+            .missing_indexes
+            .lock()
+            .unwrap()
+            .insert(index_id.to_string());
+    }
+
+    /// Configs of the indexes created through `api/v1/indexes`.
+    pub fn created_indexes(&self) -> Vec<serde_json::Value> {
+        self.state.created_indexes.lock().unwrap().clone()
+    }
+
+    pub fn received_requests_count(&self) -> usize {
+        self.received_requests().len()
+    }
+
+    pub fn received_requests(&self) -> Vec<ReceivedRequest> {
+        self.state
+            .received_requests
+            .lock()
+            .expect("Failed to acquire a lock on `ServerState.received_requests`!")
+            .clone()
     }
 
     pub fn accepted_requests(&self) -> Vec<serde_json::Value> {
-        self.events
+        self.state
+            .events
             .lock()
-            .expect("Failed to acquire a lock on `TestHttpServer.events`!")
+            .expect("Failed to acquire a lock on `ServerState.events`!")
             .iter()
             .map(|raw_event| {
                 serde_json::from_str(raw_event).expect("Failed to deserialize event body!")
@@ -87,6 +130,201 @@ impl TestHttpServer {
     }
 }
 
+impl TestHttpServer {
+    /// Accepted events without the timestamp the layer adds to each of them, which is checked to
+    /// be there.
+    pub fn accepted_requests_without_timestamps(&self) -> Vec<serde_json::Value> {
+        self.accepted_requests()
+            .into_iter()
+            .map(|mut event| {
+                let timestamp = event
+                    .as_object_mut()
+                    .and_then(|event| event.remove("timestamp"));
+                assert!(
+                    timestamp.as_ref().is_some_and(serde_json::Value::is_string),
+                    "Event has no timestamp: {}",
+                    event,
+                );
+                event
+            })
+            .collect()
+    }
+}
+
+impl ServerState {
+    async fn handle(&self, request: Request<Body>) -> Result<Response<Body>, hyper::Error> {
+        let received_request = ReceivedRequest {
+            method: request.method().to_string(),
+            path_and_query: request
+                .uri()
+                .path_and_query()
+                .map(|path_and_query| path_and_query.to_string())
+                .unwrap_or_default(),
+            headers: request
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    let value = String::from_utf8_lossy(value.as_bytes()).to_string();
+                    (name.to_string(), value)
+                })
+                .collect(),
+        };
+        let content_encoding = received_request.headers.get("content-encoding").cloned();
+        let is_bulk = received_request
+            .path_and_query
+            .contains("/api/v1/_elastic/_bulk");
+        let path = request.uri().path().to_string();
+        let method = request.method().clone();
+        self.received_requests
+            .lock()
+            .unwrap()
+            .push(received_request);
+        let body_bytes = hyper::body::to_bytes(request.into_body()).await?;
+        let status = self
+            .canned_statuses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .map(|status| StatusCode::from_u16(status).unwrap())
+            .unwrap_or(StatusCode::OK);
+        if !status.is_success() {
+            let mut response = Response::new(Body::from("Canned failure"));
+            *response.status_mut() = status;
+            return Ok(response);
+        }
+        if method == Method::GET {
+            return Ok(self.handle_get(&path));
+        }
+        if path.ends_with("/api/v1/indexes") {
+            return Ok(self.create_index(&body_bytes));
+        }
+        if let Some(index_id) = ingested_index_id(&path) {
+            if self.missing_indexes.lock().unwrap().contains(index_id) {
+                return Ok(not_found(index_id));
+            }
+        }
+        let body = decode(&body_bytes, content_encoding.as_deref());
+        if is_bulk {
+            return Ok(self.handle_bulk(&body));
+        }
+        let mut events = self.events.lock().unwrap();
+        let mut num_docs_for_processing = 0;
+        let mut parse_failures = Vec::new();
+        for raw_event in body.lines() {
+            num_docs_for_processing += 1;
+            // Imitates a doc mapping that doesn't accept this field.
+            if raw_event.contains(REJECTED_FIELD) {
+                parse_failures.push(json!({
+                    "document": raw_event,
+                    "message": format!("field `{}` is not allowed", REJECTED_FIELD),
+                    "reason": "invalid_schema",
+                }));
+                continue;
+            }
+            events.push(raw_event.to_string());
+        }
+        if events.len() >= self.expected_events_count {
+            self.processed_all.notify_one();
+        }
+        let response_body = json!({
+            "num_docs_for_processing": num_docs_for_processing,
+            "num_ingested_docs": num_docs_for_processing - parse_failures.len(),
+            "num_rejected_docs": parse_failures.len(),
+            "parse_failures": parse_failures,
+        });
+        Ok(Response::new(Body::from(response_body.to_string())))
+    }
+
+    fn create_index(&self, body_bytes: &[u8]) -> Response<Body> {
+        let index_config: serde_json::Value =
+            serde_json::from_slice(body_bytes).expect("Failed to deserialize index config!");
+        let index_id = index_config["index_id"].as_str().unwrap_or_default();
+        if !self.missing_indexes.lock().unwrap().remove(index_id) {
+            let mut response =
+                Response::new(Body::from(format!("index `{}` already exists", index_id)));
+            *response.status_mut() = StatusCode::BAD_REQUEST;
+            return response;
+        }
+        let response = Response::new(Body::from(index_config.to_string()));
+        self.created_indexes.lock().unwrap().push(index_config);
+        response
+    }
+
+    // Answers health checks and index metadata requests.
+    fn handle_get(&self, path: &str) -> Response<Body> {
+        if path.ends_with("/health/livez") {
+            return Response::new(Body::from("true"));
+        }
+        let Some((_, index_id)) = path.split_once("/api/v1/indexes/") else {
+            return not_found(path);
+        };
+        if self.missing_indexes.lock().unwrap().contains(index_id) {
+            return not_found(index_id);
+        }
+        let index_metadata = json!({"index_config": {"index_id": index_id}});
+        Response::new(Body::from(index_metadata.to_string()))
+    }
+
+    // Answers the way Quickwit's Elasticsearch-compatible `_bulk` endpoint does.
+    fn handle_bulk(&self, body: &str) -> Response<Body> {
+        let mut events = self.events.lock().unwrap();
+        let mut items = Vec::new();
+        let mut lines = body.lines();
+        while let (Some(action), Some(raw_event)) = (lines.next(), lines.next()) {
+            let action: serde_json::Value =
+                serde_json::from_str(action).expect("Failed to deserialize bulk action!");
+            let index_id = action["create"]["_index"].clone();
+            if raw_event.contains(REJECTED_FIELD) {
+                items.push(json!({"create": {
+                    "_index": index_id,
+                    "status": 400,
+                    "error": {
+                        "type": "mapper_parsing_exception",
+                        "reason": format!("field `{}` is not allowed", REJECTED_FIELD),
+                    },
+                }}));
+                continue;
+            }
+            items.push(json!({"create": {"_index": index_id, "status": 201}}));
+            events.push(raw_event.to_string());
+        }
+        if events.len() >= self.expected_events_count {
+            self.processed_all.notify_one();
+        }
+        let errors = items.iter().any(|item| item["create"]["status"] != 201);
+        let response_body = json!({"took": 1, "errors": errors, "items": items});
+        Response::new(Body::from(response_body.to_string()))
+    }
+}
+
+fn ingested_index_id(path: &str) -> Option<&str> {
+    let (_, index_path) = path.split_once("/api/v1/")?;
+    index_path.strip_suffix("/ingest")
+}
+
+fn not_found(what: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(format!("`{}` not found", what)));
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    response
+}
+
+fn decode(body_bytes: &[u8], content_encoding: Option<&str>) -> String {
+    match content_encoding {
+        Some("gzip") => {
+            let mut body = String::new();
+            GzDecoder::new(body_bytes)
+                .read_to_string(&mut body)
+                .expect("Failed to decompress gzip body!");
+            body
+        }
+        Some("zstd") => {
+            let body = zstd::decode_all(body_bytes).expect("Failed to decompress zstd body!");
+            String::from_utf8(body).expect("Decompressed body isn't UTF-8!")
+        }
+        _ => String::from_utf8_lossy(body_bytes).to_string(),
+    }
+}
+
 impl Drop for TestHttpServer {
     fn drop(&mut self) {
         if let Some(shutdown_trigger) = self.shutdown_trigger.take() {
@@ -0,0 +1,65 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use serde_json::json;
+use tracing_quickwit::{Compression, QuickwitLoggingLayerBuilder};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+async fn send_compressed_logs(compression: Compression, port: u16) -> TestHttpServer {
+    let quickwit_server = TestHttpServer::new(port, 2, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let (layer, handle, background_task) = QuickwitLoggingLayerBuilder::new(
+        Url::parse(&format!("http://127.0.0.1:{}", port)).unwrap(),
+    )
+    .marker_field("task")
+    .map_marker_to_index("billing", "billing_logs")
+    .with_compression(compression)
+    .build();
+    tokio::spawn(background_task);
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info!(task = "billing", number = 1_u64);
+        tracing::info!(task = "billing", number = 2_u64);
+    });
+    handle.flush().await;
+
+    assert_eq!(
+        quickwit_server.accepted_requests_without_timestamps(),
+        vec![
+            json!({"task": "billing", "number": 1}),
+            json!({"task": "billing", "number": 2}),
+        ],
+    );
+    quickwit_server
+}
+
+#[tokio::test]
+async fn send_gzip_compressed_batches() {
+    let quickwit_server = send_compressed_logs(Compression::Gzip, 9034).await;
+
+    let requests = quickwit_server.received_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0]
+            .headers
+            .get("content-encoding")
+            .map(String::as_str),
+        Some("gzip"),
+    );
+}
+
+#[tokio::test]
+async fn send_zstd_compressed_batches() {
+    let quickwit_server = send_compressed_logs(Compression::Zstd, 9035).await;
+
+    let requests = quickwit_server.received_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0]
+            .headers
+            .get("content-encoding")
+            .map(String::as_str),
+        Some("zstd"),
+    );
+}
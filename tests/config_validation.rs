@@ -0,0 +1,95 @@
+use tracing_quickwit::{ConfigProblem, QuickwitLoggingLayerBuilder};
+use url::Url;
+
+#[test]
+fn report_every_config_problem() {
+    let quickwit_url = Url::parse("ftp://127.0.0.1/quickwit").unwrap();
+    let result = QuickwitLoggingLayerBuilder::new(quickwit_url)
+        .map_marker_to_index("billing", "9-billing logs")
+        .with_batch_size(0)
+        .try_build();
+
+    let Err(err) = result else {
+        panic!("Invalid configuration was accepted!");
+    };
+    assert!(matches!(
+        err.problems.as_slice(),
+        [
+            ConfigProblem::EmptyMarkerField,
+            ConfigProblem::ZeroBatchSize,
+            ConfigProblem::UnsupportedScheme { scheme },
+            ConfigProblem::InvalidIndexId { index_id },
+        ] if scheme == "ftp" && index_id == "9-billing logs"
+    ));
+}
+
+#[test]
+fn reject_url_that_cannot_be_a_base() {
+    let quickwit_url = Url::parse("mailto:quickwit@example.com").unwrap();
+    let result = QuickwitLoggingLayerBuilder::new(quickwit_url)
+        .marker_field("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .try_build();
+
+    let Err(err) = result else {
+        panic!("Invalid configuration was accepted!");
+    };
+    assert!(matches!(
+        err.problems.as_slice(),
+        [
+            ConfigProblem::UnsupportedScheme { .. },
+            ConfigProblem::InvalidUrl { .. },
+        ]
+    ));
+}
+
+#[test]
+fn accept_valid_config() {
+    let result = QuickwitLoggingLayerBuilder::new(Url::parse("https://127.0.0.1:7280").unwrap())
+        .marker_field("task")
+        .map_marker_to_index("billing", "billing_logs.v2")
+        .try_build();
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn reject_headers_that_cannot_be_sent() {
+    let result = QuickwitLoggingLayerBuilder::new(Url::parse("https://127.0.0.1:7280").unwrap())
+        .marker_field("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .with_header("X-Tenant", "billing\nteam")
+        .with_header("X Tenant", "billing")
+        .with_header("X-Team", "billing")
+        .try_build();
+
+    let Err(err) = result else {
+        panic!("Invalid configuration was accepted!");
+    };
+    assert!(matches!(
+        err.problems.as_slice(),
+        [
+            ConfigProblem::InvalidHeader { name: value_name },
+            ConfigProblem::InvalidHeader { name },
+        ] if value_name == "X-Tenant" && name == "X Tenant"
+    ));
+}
+
+#[test]
+fn build_despite_problems_that_dont_prevent_running() {
+    let quickwit_url = Url::parse("https://127.0.0.1:7280").unwrap();
+    let (_layer, _handle, _background_task) = QuickwitLoggingLayerBuilder::new(quickwit_url)
+        .with_batch_size(0)
+        .build();
+}
+
+#[test]
+#[should_panic(expected = "header `X-Tenant` has an invalid name or value")]
+fn panic_on_problems_that_prevent_running() {
+    let (_layer, _handle, _background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("https://127.0.0.1:7280").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_header("X-Tenant", "billing\nteam")
+            .build();
+}
@@ -0,0 +1,136 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tracing_quickwit::{ConfigProblem, QuickwitError, QuickwitLoggingLayerBuilder, RetryPolicy};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+#[tokio::test]
+async fn send_requests_with_custom_http_client() {
+    let quickwit_server = TestHttpServer::new(9038, 1, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let http_client = reqwest::Client::builder()
+        .user_agent("billing-service/1.0")
+        .build()
+        .unwrap();
+    let (layer, handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9038").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_http_client(http_client)
+            .with_request_timeout(Duration::from_secs(5))
+            .build();
+    tokio::spawn(background_task);
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info!(task = "billing", number = 1_u64);
+    });
+    handle.flush().await;
+
+    let requests = quickwit_server.received_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].headers.get("user-agent").map(String::as_str),
+        Some("billing-service/1.0"),
+    );
+}
+
+#[tokio::test]
+async fn time_out_requests_quickwit_never_answers() {
+    let listener = TcpListener::bind("127.0.0.1:9065").await.unwrap();
+    // Accepts connections and keeps them open without ever answering.
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((connection, _)) = listener.accept().await {
+            connections.push(connection);
+        }
+    });
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors_clone = Arc::clone(&errors);
+    let (layer, handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9065").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_retry_policy(RetryPolicy::never())
+            .with_request_timeout(Duration::from_millis(200))
+            .on_error(move |err| errors_clone.lock().unwrap().push(err))
+            .build();
+    tokio::spawn(background_task);
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info!(task = "billing", number = 1_u64);
+    });
+    let started = Instant::now();
+    handle.flush().await;
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    let errors = errors.lock().unwrap();
+    assert!(matches!(
+        errors.as_slice(),
+        [QuickwitError::Transport { index_id, attempt: 1, source, .. }]
+            if index_id == "billing_logs"
+                && source
+                    .downcast_ref::<Arc<reqwest::Error>>()
+                    .is_some_and(|err| err.is_timeout()),
+    ));
+}
+
+#[test]
+fn reject_missing_ca_bundle_file() {
+    let result = QuickwitLoggingLayerBuilder::new(Url::parse("https://127.0.0.1:7280").unwrap())
+        .marker_field("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .with_ca_bundle_file("/nonexistent/quickwit-ca.pem")
+        .try_build();
+
+    let Err(err) = result else {
+        panic!("Missing CA bundle was accepted!");
+    };
+    assert!(matches!(
+        err.problems.as_slice(),
+        [ConfigProblem::HttpClient { source }]
+            if source.downcast_ref::<std::io::Error>().map(std::io::Error::kind)
+                == Some(std::io::ErrorKind::NotFound),
+    ));
+}
+
+#[test]
+fn reject_invalid_ca_bundle() {
+    let result = QuickwitLoggingLayerBuilder::new(Url::parse("https://127.0.0.1:7280").unwrap())
+        .marker_field("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .with_ca_bundle("-----BEGIN CERTIFICATE-----\nnot base64\n-----END CERTIFICATE-----\n")
+        .try_build();
+
+    let Err(err) = result else {
+        panic!("Invalid CA bundle was accepted!");
+    };
+    assert!(matches!(
+        err.problems.as_slice(),
+        [ConfigProblem::HttpClient { .. }]
+    ));
+}
+
+#[test]
+fn report_options_ignored_for_custom_http_client() {
+    let result = QuickwitLoggingLayerBuilder::new(Url::parse("https://127.0.0.1:7280").unwrap())
+        .marker_field("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .with_http_client(reqwest::Client::new())
+        .with_connect_timeout(Duration::from_secs(1))
+        .with_ca_bundle_file("/nonexistent/quickwit-ca.pem")
+        .with_request_timeout(Duration::from_secs(5))
+        .try_build();
+
+    let Err(err) = result else {
+        panic!("Ignored options weren't reported!");
+    };
+    assert!(matches!(
+        err.problems.as_slice(),
+        [ConfigProblem::OverriddenByHttpClient { options }]
+            if options == &["with_connect_timeout", "with_ca_bundle_file"],
+    ));
+}
@@ -0,0 +1,161 @@
+use serde_json::json;
+use tracing_quickwit::{
+    DocMappingRecorder, FieldMapping, FieldType, MetadataField, MetadataLayout, SpanLayout,
+    TimestampFormat,
+};
+use tracing_subscriber::layer::SubscriberExt;
+
+#[test]
+fn infer_field_types_from_recorded_values() {
+    let recorder = DocMappingRecorder::new("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .map_marker_to_index("audit", "audit_logs");
+    let subscriber = tracing_subscriber::registry().with(recorder.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(
+            task = "billing",
+            amount = 42_u64,
+            note = tracing::field::Empty
+        );
+        tracing::info!(task = "billing", amount = 3.5_f64);
+        tracing::info!(task = "billing", retries = 2_u64);
+        tracing::info!(task = "unmapped", ignored = 1_u64);
+        tracing::info_span!("refund", task = "billing", order_id = 7_u64).in_scope(|| {
+            tracing::info!(refunded = true);
+        });
+    });
+
+    let index_configs = recorder.index_configs();
+    assert_eq!(index_configs.len(), 2);
+    assert_eq!(
+        index_configs["billing_logs"].field_mappings(),
+        &[
+            FieldMapping::new("amount", FieldType::F64),
+            FieldMapping::new("note", FieldType::Text),
+            FieldMapping::new("order_id", FieldType::U64),
+            FieldMapping::new("refunded", FieldType::Bool),
+            FieldMapping::new("retries", FieldType::U64),
+            FieldMapping::new("task", FieldType::Text),
+            FieldMapping::new("timestamp", FieldType::Datetime).fast(),
+        ],
+    );
+    assert_eq!(
+        index_configs["audit_logs"].to_json("audit_logs"),
+        json!({
+            "version": "0.8",
+            "index_id": "audit_logs",
+            "doc_mapping": {
+                "mode": "dynamic",
+                "field_mappings": [{
+                    "name": "timestamp",
+                    "type": "datetime",
+                    "fast": true,
+                    "input_formats": ["rfc3339"],
+                    "output_format": "rfc3339",
+                }],
+                "timestamp_field": "timestamp",
+            },
+        }),
+    );
+}
+
+#[test]
+fn map_configured_timestamp_field() {
+    let recorder = DocMappingRecorder::new("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .with_timestamp_field("recorded_at")
+        .with_timestamp_format(TimestampFormat::UnixMillis);
+    let subscriber = tracing_subscriber::registry().with(recorder.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(task = "billing", amount = 42_u64);
+    });
+
+    let doc_mapping =
+        &recorder.index_configs()["billing_logs"].to_json("billing_logs")["doc_mapping"];
+    assert_eq!(doc_mapping["timestamp_field"], "recorded_at");
+    assert_eq!(
+        doc_mapping["field_mappings"][2],
+        json!({
+            "name": "recorded_at",
+            "type": "datetime",
+            "fast": true,
+            "input_formats": ["unix_timestamp"],
+            "output_format": "unix_timestamp_millis",
+        }),
+    );
+
+    let recorder = DocMappingRecorder::new("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .without_timestamp();
+    let doc_mapping =
+        &recorder.index_configs()["billing_logs"].to_json("billing_logs")["doc_mapping"];
+    assert_eq!(doc_mapping.get("timestamp_field"), None);
+    assert_eq!(doc_mapping["field_mappings"], json!([]));
+}
+
+#[test]
+fn map_nested_span_fields_as_json() {
+    let recorder = DocMappingRecorder::new("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .with_span_layout(SpanLayout::Nested("spans".to_string()))
+        .without_timestamp();
+    let subscriber = tracing_subscriber::registry().with(recorder.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("refund", task = "billing", order_id = 7_u64).in_scope(|| {
+            tracing::info!(refunded = true);
+        });
+    });
+
+    assert_eq!(
+        recorder.index_configs()["billing_logs"].field_mappings(),
+        &[
+            FieldMapping::new("refunded", FieldType::Bool),
+            FieldMapping::new("spans", FieldType::Json),
+        ],
+    );
+}
+
+#[test]
+fn map_metadata_fields() {
+    let recorder = DocMappingRecorder::new("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .map_marker_to_index("audit", "audit_logs")
+        .with_metadata_field(MetadataField::Level)
+        .with_metadata_field_as(MetadataField::Line, "source_line")
+        .without_timestamp();
+    let nested_recorder = DocMappingRecorder::new("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .with_metadata_field(MetadataField::Level)
+        .with_metadata_layout(MetadataLayout::Nested("metadata".to_string()))
+        .without_timestamp();
+    let subscriber = tracing_subscriber::registry()
+        .with(recorder.clone())
+        .with(nested_recorder.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(task = "billing", amount = -3_i64);
+        tracing::info!(task = "billing", amount = 2_u64);
+    });
+
+    assert_eq!(
+        recorder.index_configs()["billing_logs"].field_mappings(),
+        &[
+            FieldMapping::new("amount", FieldType::I64),
+            FieldMapping::new("level", FieldType::Text),
+            FieldMapping::new("source_line", FieldType::U64),
+            FieldMapping::new("task", FieldType::Text),
+        ],
+    );
+    assert_eq!(recorder.index_configs()["audit_logs"].field_mappings(), &[]);
+    assert_eq!(
+        nested_recorder.index_configs()["billing_logs"].field_mappings(),
+        &[
+            FieldMapping::new("amount", FieldType::I64),
+            FieldMapping::new("metadata", FieldType::Json),
+            FieldMapping::new("task", FieldType::Text),
+        ],
+    );
+}
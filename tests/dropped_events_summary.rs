@@ -0,0 +1,62 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tracing_quickwit::{DropReason, DroppedEventsCount, QuickwitLoggingLayerBuilder};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+#[tokio::test]
+async fn count_dropped_events_and_report_them_to_index() {
+    let quickwit_server = TestHttpServer::new(9033, 2, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_clone = Arc::clone(&reports);
+    let (layer, handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9033").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_channel_capacity(1)
+            .with_dropped_events_summary(true)
+            .on_ingest_report(move |report| reports_clone.lock().unwrap().push(report.clone()))
+            .build();
+
+    // The background task isn't running yet, so the channel can't be drained.
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        for number in 0..3_u64 {
+            tracing::info!(task = "billing", number);
+        }
+    });
+    background_task.await;
+
+    assert_eq!(
+        handle.dropped_events(),
+        vec![DroppedEventsCount {
+            index_id: "billing_logs".to_string(),
+            reason: DropReason::ChannelFull,
+            count: 2,
+        }],
+    );
+    let accepted_requests = quickwit_server.accepted_requests_without_timestamps();
+    assert_eq!(accepted_requests.len(), 2);
+    let summary = &accepted_requests[0];
+    assert_eq!(summary["dropped_events"], json!(2));
+    assert!(summary["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("2 events dropped between "));
+    assert!(
+        summary["dropped_from"].as_str().unwrap() <= summary["dropped_until"].as_str().unwrap()
+    );
+    assert_eq!(
+        accepted_requests[1],
+        json!({"task": "billing", "number": 0})
+    );
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 1);
+    // The summary counts as a document of the batch.
+    assert_eq!(reports[0].batch_size, 2);
+    assert_eq!(reports[0].accepted(), 2);
+    assert_eq!(reports[0].rejected(), 0);
+}
@@ -0,0 +1,69 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use serde_json::json;
+use tracing_quickwit::{MetadataField, MetadataLayout, QuickwitLoggingLayerBuilder};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+async fn send_log(builder: QuickwitLoggingLayerBuilder, quickwit_server: &TestHttpServer) -> u32 {
+    quickwit_server.wait_until_ready().await;
+    let (layer, _handle, background_task) = builder
+        .marker_field("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .without_timestamp()
+        .build();
+
+    let line =
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            tracing::warn!(task = "billing", number = 1_u64);
+            line!() - 1
+        });
+    background_task.await;
+    line
+}
+
+#[tokio::test]
+async fn flatten_metadata_fields() {
+    let quickwit_server = TestHttpServer::new(9048, 1, Vec::new());
+    let builder = QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9048").unwrap())
+        .with_metadata_field_as(MetadataField::Level, "severity")
+        .with_metadata_field(MetadataField::Target)
+        .with_metadata_field(MetadataField::Line);
+    let line = send_log(builder, &quickwit_server).await;
+
+    assert_eq!(
+        quickwit_server.accepted_requests(),
+        vec![json!({
+            "task": "billing",
+            "number": 1,
+            "severity": "WARN",
+            "target": "event_metadata",
+            "line": line,
+        })],
+    );
+}
+
+#[tokio::test]
+async fn nest_metadata_fields() {
+    let quickwit_server = TestHttpServer::new(9049, 1, Vec::new());
+    let builder = QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9049").unwrap())
+        .with_metadata_field(MetadataField::Level)
+        .with_metadata_field(MetadataField::ModulePath)
+        .with_metadata_field(MetadataField::File)
+        .with_metadata_layout(MetadataLayout::Nested("metadata".to_string()));
+    send_log(builder, &quickwit_server).await;
+
+    assert_eq!(
+        quickwit_server.accepted_requests(),
+        vec![json!({
+            "task": "billing",
+            "number": 1,
+            "metadata": {
+                "level": "WARN",
+                "module_path": "event_metadata",
+                "file": "tests/event_metadata.rs",
+            },
+        })],
+    );
+}
@@ -0,0 +1,61 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+use serde_json::json;
+use std::time::Duration;
+use tracing_quickwit::FlushReport;
+
+#[tokio::test]
+async fn flush_and_shutdown_through_handle() {
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(100)
+        .with_expected_recieved_events_count(4)
+        .with_quickwit_port(9027)
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("marker_field_value", "some_index_id")
+        .build()
+        .await;
+
+    tracing::info!(some_marker_field = "marker_field_value", metric = 1_u64);
+    tracing::info!(some_marker_field = "marker_field_value", metric = 2_u64);
+    tracing::info!(some_marker_field = "marker_field_value", metric = 3_u64);
+
+    let report = env.quickwit_handle.flush().await;
+
+    assert_eq!(
+        report,
+        FlushReport {
+            delivered: 3,
+            lost: 0,
+        },
+    );
+    assert_eq!(env.quickwit_server.accepted_requests().len(), 3);
+
+    tracing::info!(some_marker_field = "marker_field_value", metric = 4_u64);
+
+    let report = env
+        .quickwit_handle
+        .shutdown(Duration::from_secs(5))
+        .await
+        .expect("Background task didn't shut down in time!");
+
+    assert_eq!(
+        report,
+        FlushReport {
+            delivered: 1,
+            lost: 0,
+        },
+    );
+    tracing::info!(some_marker_field = "marker_field_value", metric = 5_u64);
+    assert_eq!(env.quickwit_handle.flush().await, FlushReport::default());
+    let expected_requests = vec![
+        json!({"some_marker_field": "marker_field_value", "metric": 1}),
+        json!({"some_marker_field": "marker_field_value", "metric": 2}),
+        json!({"some_marker_field": "marker_field_value", "metric": 3}),
+        json!({"some_marker_field": "marker_field_value", "metric": 4}),
+    ];
+    assert_eq!(
+        env.quickwit_server.accepted_requests_without_timestamps(),
+        expected_requests
+    );
+}
@@ -0,0 +1,41 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn flush_partially_filled_batch_after_interval() {
+    let flush_interval = Duration::from_millis(200);
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(10)
+        .with_flush_interval(flush_interval)
+        .with_expected_recieved_events_count(2)
+        .with_quickwit_port(9026)
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("marker_field_value", "some_index_id")
+        .build()
+        .await;
+
+    let emitted_at = Instant::now();
+    tracing::info!(some_marker_field = "marker_field_value", metric = "first");
+    tracing::info!(some_marker_field = "marker_field_value", metric = "second");
+
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        env.quickwit_server
+            .wait_until_processed_expected_events_count(),
+    )
+    .await
+    .expect("Partially filled batch was never flushed!");
+
+    assert!(emitted_at.elapsed() >= flush_interval);
+    let expected_requests = vec![
+        json!({"some_marker_field": "marker_field_value", "metric": "first"}),
+        json!({"some_marker_field": "marker_field_value", "metric": "second"}),
+    ];
+    assert_eq!(
+        env.quickwit_server.accepted_requests_without_timestamps(),
+        expected_requests
+    );
+}
@@ -1,12 +1,12 @@
 pub mod common;
 
 use common::environment::TestEnvironment;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use tracing_quickwit::QuickwitError;
 
 #[tokio::test]
 async fn handle_missing_index() {
-    let missed = Arc::new(AtomicBool::new(false));
+    let missed = Arc::new(Mutex::new(None));
     let missed_clone = Arc::clone(&missed);
     let env = TestEnvironment::builder()
         .with_quickwit_subscriber_channel_capacity(1)
@@ -15,7 +15,11 @@ async fn handle_missing_index() {
         .with_quickwit_port(9025)
         .with_marker_field("some_marker_field")
         .with_marker_to_index_mapping("marker_field_value", "some_index_id")
-        .on_index_missing(move || missed_clone.store(true, Ordering::Relaxed))
+        .on_error(move |err| {
+            if let QuickwitError::UnmappedMarker { marker_value } = err {
+                *missed_clone.lock().unwrap() = Some(marker_value);
+            }
+        })
         .build()
         .await;
 
@@ -27,8 +31,11 @@ async fn handle_missing_index() {
     env.wait_until_all_events_emitted().await;
 
     assert_eq!(
-        env.quickwit_server.accepted_requests(),
+        env.quickwit_server.accepted_requests_without_timestamps(),
         Vec::<serde_json::Value>::new(),
     );
-    assert_eq!(missed.load(Ordering::Relaxed), true);
+    assert_eq!(
+        missed.lock().unwrap().as_deref(),
+        Some("WRONG_marker_field_value"),
+    );
 }
@@ -0,0 +1,113 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use serde_json::json;
+use std::time::Duration;
+use tracing_quickwit::{
+    FieldMapping, FieldType, IndexConfig, QuickwitError, QuickwitLoggingLayerBuilder,
+};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+fn billing_index_config() -> IndexConfig {
+    IndexConfig::new()
+        .with_field_mapping(FieldMapping::new("task", FieldType::Text).fast())
+        .with_timestamp_field("timestamp")
+        .with_retention(Duration::from_secs(30 * 24 * 60 * 60))
+}
+
+#[tokio::test]
+async fn create_missing_index_on_first_flush() {
+    let quickwit_server = TestHttpServer::new(9045, 1, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    quickwit_server.mark_index_missing("billing_logs");
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9045").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_index_config("billing_logs", billing_index_config())
+            .build();
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info!(task = "billing", metric = "done");
+    });
+    // Dropping the layer makes the background task flush and return.
+    background_task.await;
+
+    assert_eq!(quickwit_server.accepted_requests().len(), 1);
+    assert_eq!(
+        quickwit_server.created_indexes(),
+        vec![json!({
+            "version": "0.8",
+            "index_id": "billing_logs",
+            "doc_mapping": {
+                "mode": "dynamic",
+                "field_mappings": [
+                    {"name": "task", "type": "text", "fast": true},
+                    {
+                        "name": "timestamp",
+                        "type": "datetime",
+                        "fast": true,
+                        "input_formats": ["rfc3339", "unix_timestamp"],
+                    },
+                ],
+                "timestamp_field": "timestamp",
+            },
+            "retention": {"period": "30days", "schedule": "hourly"},
+        })],
+    );
+}
+
+#[tokio::test]
+async fn create_missing_indexes_at_startup() {
+    let quickwit_server = TestHttpServer::new(9046, 0, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    quickwit_server.mark_index_missing("billing_logs");
+    let (_layer, handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9046").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .map_marker_to_index("audit", "audit_logs")
+            .with_index_config("billing_logs", billing_index_config())
+            .with_index_config("audit_logs", IndexConfig::new())
+            .build();
+    tokio::spawn(background_task);
+
+    assert_eq!(
+        handle.create_missing_indexes().await.unwrap(),
+        vec!["billing_logs".to_string()],
+    );
+    assert_eq!(
+        handle.create_missing_indexes().await.unwrap(),
+        Vec::<String>::new(),
+    );
+    assert_eq!(quickwit_server.created_indexes().len(), 1);
+}
+
+#[tokio::test]
+async fn only_create_indexes_quickwit_reports_missing() {
+    let quickwit_server = TestHttpServer::new(9061, 0, vec![403]);
+    quickwit_server.wait_until_ready().await;
+    quickwit_server.mark_index_missing("billing_logs");
+    let (_layer, handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9061").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_index_config("billing_logs", billing_index_config())
+            .build();
+    tokio::spawn(background_task);
+
+    let err = handle.create_missing_indexes().await.unwrap_err();
+    assert!(matches!(
+        &err,
+        QuickwitError::IndexCreation { index_id, reason }
+            if index_id == "billing_logs" && reason.contains("403")
+    ));
+    let methods = quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.method)
+        .collect::<Vec<_>>();
+    assert_eq!(methods, vec!["GET".to_string()]);
+    assert!(quickwit_server.created_indexes().is_empty());
+}
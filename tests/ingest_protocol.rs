@@ -0,0 +1,110 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+use common::quickwit::TestHttpServer;
+use std::sync::{Arc, Mutex};
+use tracing_quickwit::{CommitMode, IngestProtocol, ParseFailure, QuickwitLoggingLayerBuilder};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+#[tokio::test]
+async fn elastic_bulk_protocol() {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_clone = Arc::clone(&reports);
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(2)
+        .with_quickwit_port(9040)
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("marker_field_value", "some_index_id")
+        .with_ingest_protocol(IngestProtocol::ElasticBulk)
+        .with_commit_mode(CommitMode::Force)
+        .on_ingest_report(move |report| reports_clone.lock().unwrap().push(report.clone()))
+        .build()
+        .await;
+
+    tracing::info!(some_marker_field = "marker_field_value", metric = "first");
+    tracing::info!(
+        some_marker_field = "marker_field_value",
+        rejected_by_doc_mapping = true,
+    );
+    env.quickwit_handle.flush().await;
+
+    let paths = env
+        .quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.path_and_query)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec!["/api/v1/_elastic/_bulk?refresh=true".to_string()]
+    );
+    assert_eq!(
+        env.quickwit_server.accepted_requests_without_timestamps(),
+        vec![serde_json::json!({
+            "some_marker_field": "marker_field_value",
+            "metric": "first",
+        })],
+    );
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!(report.index_id, "some_index_id");
+    assert_eq!(report.batch_size, 2);
+    assert_eq!(report.accepted(), 1);
+    assert_eq!(report.rejected(), 1);
+    assert_eq!(report.parse_failures[0].reason, "mapper_parsing_exception");
+}
+
+#[tokio::test]
+async fn ingest_v2_protocol() {
+    let quickwit_server = TestHttpServer::new(9057, 2, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_clone = Arc::clone(&reports);
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9057").unwrap())
+            .marker_field("some_marker_field")
+            .map_marker_to_index("marker_field_value", "some_index_id")
+            .with_ingest_protocol(IngestProtocol::IngestV2)
+            .with_batch_size(10)
+            .without_timestamp()
+            .on_ingest_report(move |report| reports_clone.lock().unwrap().push(report.clone()))
+            .build();
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info!(some_marker_field = "marker_field_value", metric = "first");
+        tracing::info!(
+            some_marker_field = "marker_field_value",
+            rejected_by_doc_mapping = true,
+        );
+    });
+    background_task.await;
+
+    let paths = quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.path_and_query)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec!["/api/v1/some_index_id/ingest?detailed_response=true".to_string()]
+    );
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!(report.index_id, "some_index_id");
+    assert_eq!(report.batch_size, 2);
+    assert_eq!(report.accepted(), 1);
+    assert_eq!(report.rejected(), 1);
+    assert_eq!(
+        report.parse_failures,
+        vec![ParseFailure {
+            document:
+                r#"{"rejected_by_doc_mapping":"true","some_marker_field":"marker_field_value"}"#
+                    .to_string(),
+            message: "field `rejected_by_doc_mapping` is not allowed".to_string(),
+            reason: "invalid_schema".to_string(),
+        }],
+    );
+}
@@ -0,0 +1,55 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+use std::sync::{Arc, Mutex};
+use tracing_quickwit::{FlushReport, ParseFailure};
+
+#[tokio::test]
+async fn report_accepted_and_rejected_documents() {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_clone = Arc::clone(&reports);
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(10)
+        .with_quickwit_port(9030)
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("marker_field_value", "some_index_id")
+        .without_timestamp()
+        .on_ingest_report(move |report| reports_clone.lock().unwrap().push(report.clone()))
+        .build()
+        .await;
+
+    tracing::info!(some_marker_field = "marker_field_value", metric = "first");
+    tracing::info!(
+        some_marker_field = "marker_field_value",
+        rejected_by_doc_mapping = true,
+    );
+    tracing::info!(some_marker_field = "marker_field_value", metric = "third");
+    let flush_report = env.quickwit_handle.flush().await;
+
+    assert_eq!(
+        flush_report,
+        FlushReport {
+            delivered: 2,
+            lost: 1
+        }
+    );
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!(report.index_id, "some_index_id");
+    assert_eq!(report.batch_size, 3);
+    assert_eq!(report.num_docs_for_processing, 3);
+    assert_eq!(report.accepted(), 2);
+    assert_eq!(report.rejected(), 1);
+    assert_eq!(
+        report.parse_failures,
+        vec![ParseFailure {
+            document:
+                r#"{"rejected_by_doc_mapping":"true","some_marker_field":"marker_field_value"}"#
+                    .to_string(),
+            message: "field `rejected_by_doc_mapping` is not allowed".to_string(),
+            reason: "invalid_schema".to_string(),
+        }],
+    );
+}
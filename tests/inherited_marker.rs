@@ -0,0 +1,52 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use tracing_quickwit::{QuickwitLoggingLayerBuilder, SpanLayout};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+#[tracing::instrument(fields(task = "billing"))]
+fn charge() {
+    tracing::info!(step = "charged");
+    let audit = tracing::info_span!("audit", task = "audit");
+    let _audit = audit.enter();
+    tracing::info!(step = "audited");
+    tracing::info!(task = "billing", step = "receipt");
+}
+
+#[tokio::test]
+async fn inherit_marker_from_nearest_span() {
+    let quickwit_server = TestHttpServer::new(9052, 3, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9052").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .map_marker_to_index("audit", "audit_logs")
+            .with_batch_size(1)
+            .with_span_layout(SpanLayout::Omitted)
+            .build();
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), charge);
+    background_task.await;
+
+    let paths = quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.path_and_query)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "/api/v1/billing_logs/ingest".to_string(),
+            "/api/v1/audit_logs/ingest".to_string(),
+            "/api/v1/billing_logs/ingest".to_string(),
+        ],
+    );
+    let steps = quickwit_server
+        .accepted_requests()
+        .into_iter()
+        .map(|event| event["step"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(steps, vec!["charged", "audited", "receipt"]);
+}
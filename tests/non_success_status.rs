@@ -0,0 +1,43 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+use std::sync::{Arc, Mutex};
+use tracing_quickwit::{QuickwitError, RetryPolicy};
+
+#[tokio::test]
+async fn treat_non_success_status_as_failure() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors_clone = Arc::clone(&errors);
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(1)
+        .with_quickwit_port(9029)
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("marker_field_value", "some_index_id")
+        .with_quickwit_canned_statuses(vec![400, 404])
+        .with_retry_policy(RetryPolicy::never())
+        .on_error(move |err| errors_clone.lock().unwrap().push(err))
+        .build()
+        .await;
+
+    tracing::info!(some_marker_field = "marker_field_value", metric = "first");
+    tracing::info!(some_marker_field = "marker_field_value", metric = "second");
+    env.quickwit_handle.flush().await;
+
+    assert_eq!(env.quickwit_server.received_requests_count(), 2);
+    assert_eq!(
+        env.quickwit_server.accepted_requests_without_timestamps(),
+        Vec::<serde_json::Value>::new(),
+    );
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        &errors[0],
+        QuickwitError::HttpStatus { index_id, batch_size: 1, attempt: 1, status: 400, body }
+            if index_id == "some_index_id" && body == "Canned failure",
+    ));
+    assert!(matches!(
+        &errors[1],
+        QuickwitError::IndexUnknown { index_id, batch_size: 1, attempt: 1 }
+            if index_id == "some_index_id",
+    ));
+}
@@ -0,0 +1,48 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing_quickwit::{RetryPolicy, StatusClass};
+
+#[tokio::test]
+async fn retry_failed_ingest() {
+    let failed = Arc::new(AtomicBool::new(false));
+    let failed_clone = Arc::clone(&failed);
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(1)
+        .with_expected_recieved_events_count(1)
+        .with_quickwit_port(9028)
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("marker_field_value", "some_index_id")
+        .with_quickwit_canned_statuses(vec![503, 429])
+        .with_retry_policy(
+            RetryPolicy::default()
+                .with_max_attempts(3)
+                .with_base_backoff(Duration::from_millis(10))
+                .with_max_backoff(Duration::from_millis(50))
+                .retry_on([StatusClass::ServerError, StatusClass::TooManyRequests]),
+        )
+        .on_error(move |_err| failed_clone.store(true, Ordering::Relaxed))
+        .build()
+        .await;
+
+    tracing::info!(some_marker_field = "marker_field_value", metric = "done");
+
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        env.quickwit_server
+            .wait_until_processed_expected_events_count(),
+    )
+    .await
+    .expect("Batch was never retried!");
+
+    assert_eq!(env.quickwit_server.received_requests_count(), 3);
+    assert_eq!(
+        env.quickwit_server.accepted_requests_without_timestamps(),
+        vec![json!({"some_marker_field": "marker_field_value", "metric": "done"})],
+    );
+    assert!(!failed.load(Ordering::Relaxed));
+}
@@ -0,0 +1,69 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use serde_json::json;
+use tracing_quickwit::{QuickwitLoggingLayerBuilder, SpanLayout};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+async fn send_log_in_spans(
+    builder: QuickwitLoggingLayerBuilder,
+    quickwit_server: &TestHttpServer,
+) -> Vec<serde_json::Value> {
+    quickwit_server.wait_until_ready().await;
+    let (layer, _handle, background_task) = builder
+        .marker_field("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .without_timestamp()
+        .build();
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        let request = tracing::info_span!("request", request_id = "r1", user_id = "u1");
+        let _request = request.enter();
+        let charge = tracing::info_span!("charge", user_id = "u2", amount = tracing::field::Empty);
+        let _charge = charge.enter();
+        charge.record("amount", 42_u64);
+        tracing::info!(task = "billing", request_id = "overridden");
+    });
+    background_task.await;
+    quickwit_server.accepted_requests()
+}
+
+#[tokio::test]
+async fn flatten_span_fields() {
+    let quickwit_server = TestHttpServer::new(9050, 1, Vec::new());
+    let builder = QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9050").unwrap());
+
+    let accepted_requests = send_log_in_spans(builder, &quickwit_server).await;
+
+    assert_eq!(
+        accepted_requests,
+        vec![json!({
+            "task": "billing",
+            "request_id": "overridden",
+            "user_id": "u2",
+            "amount": 42,
+        })],
+    );
+}
+
+#[tokio::test]
+async fn nest_span_fields() {
+    let quickwit_server = TestHttpServer::new(9051, 1, Vec::new());
+    let builder = QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9051").unwrap())
+        .with_span_layout(SpanLayout::Nested("spans".to_string()));
+
+    let accepted_requests = send_log_in_spans(builder, &quickwit_server).await;
+
+    assert_eq!(
+        accepted_requests,
+        vec![json!({
+            "task": "billing",
+            "request_id": "overridden",
+            "spans": [
+                {"name": "request", "fields": {"request_id": "r1", "user_id": "u1"}},
+                {"name": "charge", "fields": {"user_id": "u2", "amount": 42}},
+            ],
+        })],
+    );
+}
@@ -0,0 +1,40 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing_quickwit::{QuickwitLoggingLayerBuilder, TimestampFormat};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[tokio::test]
+async fn record_timestamp_in_configured_field_and_format() {
+    let quickwit_server = TestHttpServer::new(9047, 1, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9047").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_timestamp_field("recorded_at")
+            .with_timestamp_format(TimestampFormat::UnixMillis)
+            .build();
+
+    let before = unix_millis();
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info!(task = "billing", number = 1);
+    });
+    let after = unix_millis();
+    background_task.await;
+
+    let accepted_requests = quickwit_server.accepted_requests();
+    assert_eq!(accepted_requests.len(), 1);
+    let recorded_at = accepted_requests[0]["recorded_at"].as_u64().unwrap();
+    assert!((before..=after).contains(&recorded_at));
+    assert!(accepted_requests[0].get("timestamp").is_none());
+}
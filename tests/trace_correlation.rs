@@ -0,0 +1,131 @@
+#![cfg(feature = "opentelemetry")]
+
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_quickwit::{
+    DocMappingRecorder, FieldMapping, FieldType, QuickwitLoggingLayerBuilder, TraceExport,
+};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+#[tokio::test]
+async fn add_opentelemetry_ids_to_documents() {
+    let quickwit_server = TestHttpServer::new(9054, 2, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9054").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .without_timestamp()
+            .build();
+    let tracer = opentelemetry_sdk::trace::TracerProvider::builder()
+        .build()
+        .tracer("trace_correlation");
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .with(layer);
+
+    let mut expected_ids = Vec::new();
+    tracing::subscriber::with_default(subscriber, || {
+        let request = tracing::info_span!("request");
+        let _request = request.enter();
+        let charge = tracing::info_span!("charge");
+        let _charge = charge.enter();
+        tracing::info!(task = "billing", "charging");
+        for span in [&charge, &request] {
+            let context = span.context();
+            let span_context = context.span().span_context().clone();
+            expected_ids.push((
+                span_context.trace_id().to_string(),
+                span_context.span_id().to_string(),
+            ));
+        }
+        drop(_charge);
+        tracing::info!(task = "billing", "charged");
+    });
+    background_task.await;
+
+    let documents = quickwit_server.accepted_requests();
+    let [charging, charged] = documents.as_slice() else {
+        panic!("Expected two documents, got {:?}!", documents);
+    };
+    let [(charge_trace_id, charge_span_id), (request_trace_id, request_span_id)] =
+        expected_ids.as_slice()
+    else {
+        unreachable!();
+    };
+    assert_eq!(charge_trace_id, request_trace_id);
+    assert_eq!(charging["trace_id"], charge_trace_id.as_str());
+    assert_eq!(charging["span_id"], charge_span_id.as_str());
+    assert_eq!(charged["trace_id"], request_trace_id.as_str());
+    assert_eq!(charged["span_id"], request_span_id.as_str());
+}
+
+#[tokio::test]
+async fn add_exported_span_ids_to_documents() {
+    let quickwit_server = TestHttpServer::new(9055, 3, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9055").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_trace_export(TraceExport::new("billing-service"))
+            .build();
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info!(task = "billing", "outside of spans");
+        let charge = tracing::info_span!("charge");
+        let _charge = charge.enter();
+        tracing::info!(task = "billing", "charging");
+    });
+    background_task.await;
+
+    let documents = quickwit_server.accepted_requests();
+    let log = |message: &str| {
+        documents
+            .iter()
+            .find(|document| document["message"] == message)
+            .unwrap_or_else(|| panic!("No document with message {:?}!", message))
+    };
+    let span = documents
+        .iter()
+        .find(|document| document["span_name"] == "charge")
+        .expect("The span wasn't exported!");
+    assert_eq!(log("charging")["trace_id"], span["trace_id"]);
+    assert_eq!(log("charging")["span_id"], span["span_id"]);
+    assert!(log("outside of spans").get("trace_id").is_none());
+    assert!(log("outside of spans").get("span_id").is_none());
+}
+
+#[test]
+fn map_opentelemetry_ids() {
+    let recorder = DocMappingRecorder::new("task")
+        .map_marker_to_index("billing", "billing_logs")
+        .without_timestamp();
+    let tracer = opentelemetry_sdk::trace::TracerProvider::builder()
+        .build()
+        .tracer("trace_correlation");
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .with(recorder.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(task = "billing", "outside of spans");
+        tracing::info_span!("charge").in_scope(|| {
+            tracing::info!(task = "billing", "charging");
+        });
+    });
+
+    assert_eq!(
+        recorder.index_configs()["billing_logs"].field_mappings(),
+        &[
+            FieldMapping::new("message", FieldType::Text),
+            FieldMapping::new("span_id", FieldType::Text),
+            FieldMapping::new("task", FieldType::Text),
+            FieldMapping::new("trace_id", FieldType::Text),
+        ],
+    );
+}
@@ -0,0 +1,184 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tracing_quickwit::{DropReason, DroppedEventsCount, QuickwitLoggingLayerBuilder, TraceExport};
+use tracing_subscriber::layer::SubscriberExt;
+use url::Url;
+
+#[tokio::test]
+async fn export_spans_to_traces_index() {
+    let quickwit_server = TestHttpServer::new(9053, 2, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9053").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_trace_export(TraceExport::new("billing-service"))
+            .build();
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        let request = tracing::info_span!("request", request_id = "r1");
+        let _request = request.enter();
+        let charge = tracing::info_span!("charge", amount = tracing::field::Empty);
+        let _charge = charge.enter();
+        charge.record("amount", 42_u64);
+        tracing::error!(reason = "card declined", "charge failed");
+    });
+    background_task.await;
+
+    let paths = quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.path_and_query)
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec!["/api/v1/otel-traces-v0_7/ingest".to_string()]);
+    let spans = quickwit_server.accepted_requests();
+    let [charge, request] = spans.as_slice() else {
+        panic!("Expected two spans, got {:?}!", spans);
+    };
+    assert_eq!(request["span_name"], "request");
+    assert_eq!(request["is_root"], true);
+    assert_eq!(request["parent_span_id"], serde_json::Value::Null);
+    assert_eq!(request["span_attributes"], json!({"request_id": "r1"}));
+    assert_eq!(request["span_status"], json!({"code": "unset"}));
+    assert_eq!(request["service_name"], "billing-service");
+    assert_eq!(charge["span_name"], "charge");
+    assert_eq!(charge["is_root"], false);
+    assert_eq!(charge["trace_id"], request["trace_id"]);
+    assert_eq!(charge["parent_span_id"], request["span_id"]);
+    assert_eq!(charge["trace_id"].as_str().unwrap().len(), 32);
+    assert_eq!(charge["span_id"].as_str().unwrap().len(), 16);
+    assert_eq!(charge["span_attributes"], json!({"amount": 42}));
+    assert_eq!(charge["span_status"], json!({"code": "error"}));
+    assert_eq!(charge["event_names"], json!(["charge failed"]));
+    assert_eq!(
+        charge["events"][0]["event_attributes"],
+        json!({"reason": "card declined"}),
+    );
+    assert!(
+        charge["span_start_timestamp_nanos"].as_u64().unwrap()
+            <= charge["span_end_timestamp_nanos"].as_u64().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn count_dropped_spans() {
+    let quickwit_server = TestHttpServer::new(9060, 1, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let (layer, handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9060").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_channel_capacity(1)
+            .with_trace_export(TraceExport::new("billing-service"))
+            .build();
+
+    // The background task isn't running yet, so the span doesn't fit in the channel.
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info_span!("charge").in_scope(|| {
+            tracing::info!(task = "billing", "charging");
+        });
+    });
+
+    assert_eq!(
+        handle.dropped_events(),
+        vec![DroppedEventsCount {
+            index_id: "otel-traces-v0_7".to_string(),
+            reason: DropReason::ChannelFull,
+            count: 1,
+        }],
+    );
+    background_task.await;
+}
+
+#[tokio::test]
+async fn never_send_dropped_events_summaries_to_traces_index() {
+    let quickwit_server = TestHttpServer::new(9062, 2, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let dropped_clone = Arc::clone(&dropped);
+    let (layer, handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9062").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_channel_capacity(1)
+            .with_trace_export(TraceExport::new("billing-service"))
+            .with_dropped_events_summary(true)
+            .on_event_dropped(move |event| dropped_clone.lock().unwrap().push(event.clone()))
+            .build();
+
+    // The background task isn't running yet, so only the first log fits in the channel.
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info_span!("charge").in_scope(|| {
+            tracing::info!(task = "billing", "charging");
+            tracing::info!(task = "billing", "charged");
+        });
+    });
+    background_task.await;
+
+    let mut dropped_events = handle.dropped_events();
+    dropped_events.sort_by(|left, right| left.index_id.cmp(&right.index_id));
+    assert_eq!(
+        dropped_events,
+        vec![
+            DroppedEventsCount {
+                index_id: "billing_logs".to_string(),
+                reason: DropReason::ChannelFull,
+                count: 1,
+            },
+            DroppedEventsCount {
+                index_id: "otel-traces-v0_7".to_string(),
+                reason: DropReason::ChannelFull,
+                count: 1,
+            },
+        ],
+    );
+    assert_eq!(dropped.lock().unwrap().len(), 2);
+    let paths = quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.path_and_query)
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec!["/api/v1/billing_logs/ingest".to_string()]);
+    let documents = quickwit_server.accepted_requests_without_timestamps();
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0]["dropped_events"], 1);
+    assert_eq!(documents[1]["message"], "charging");
+}
+
+#[tokio::test]
+async fn only_export_filtered_spans() {
+    let quickwit_server = TestHttpServer::new(9063, 2, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    let (layer, _handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9063").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .with_trace_export(
+                TraceExport::new("billing-service")
+                    .with_span_filter(|metadata| metadata.target() != "noisy"),
+            )
+            .build();
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        let request = tracing::info_span!("request");
+        let _request = request.enter();
+        let noisy = tracing::info_span!(target: "noisy", "poll");
+        let _noisy = noisy.enter();
+        tracing::info_span!(target: "hyper::client", "connect").in_scope(|| {
+            tracing::info_span!("charge").in_scope(|| {});
+        });
+    });
+    background_task.await;
+
+    let spans = quickwit_server.accepted_requests();
+    let [charge, request] = spans.as_slice() else {
+        panic!("Expected two spans, got {:?}!", spans);
+    };
+    assert_eq!(request["span_name"], "request");
+    assert_eq!(charge["span_name"], "charge");
+    assert_eq!(charge["trace_id"], request["trace_id"]);
+    assert_eq!(charge["parent_span_id"], request["span_id"]);
+}
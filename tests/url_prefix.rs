@@ -0,0 +1,29 @@
+pub mod common;
+
+use common::environment::TestEnvironment;
+
+#[tokio::test]
+async fn keep_base_url_path_prefix() {
+    let env = TestEnvironment::builder()
+        .with_quickwit_subscriber_channel_capacity(1)
+        .with_quickwit_port(9042)
+        .with_quickwit_path_prefix("/quickwit")
+        .with_marker_field("some_marker_field")
+        .with_marker_to_index_mapping("marker_field_value", "some_index_id")
+        .build()
+        .await;
+
+    tracing::info!(some_marker_field = "marker_field_value", metric = "done");
+    env.quickwit_handle.flush().await;
+
+    let paths = env
+        .quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.path_and_query)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec!["/quickwit/api/v1/some_index_id/ingest".to_string()],
+    );
+}
@@ -0,0 +1,54 @@
+pub mod common;
+
+use common::quickwit::TestHttpServer;
+use tracing_quickwit::{QuickwitLoggingLayerBuilder, VerifyError};
+use url::Url;
+
+#[tokio::test]
+async fn report_missing_indexes() {
+    let quickwit_server = TestHttpServer::new(9043, 0, Vec::new());
+    quickwit_server.wait_until_ready().await;
+    quickwit_server.mark_index_missing("audit_logs");
+    let (_layer, handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9043").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .map_marker_to_index("audit", "audit_logs")
+            .build();
+    tokio::spawn(background_task);
+
+    let result = handle.verify().await;
+
+    assert!(matches!(
+        result,
+        Err(VerifyError::MissingIndexes { index_ids }) if index_ids == vec!["audit_logs"]
+    ));
+    let paths = quickwit_server
+        .received_requests()
+        .into_iter()
+        .map(|request| request.path_and_query)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "/health/livez".to_string(),
+            "/api/v1/indexes/audit_logs".to_string(),
+            "/api/v1/indexes/billing_logs".to_string(),
+        ],
+    );
+}
+
+#[tokio::test]
+async fn report_unreachable_cluster() {
+    // Nothing listens on this port.
+    let (_layer, handle, background_task) =
+        QuickwitLoggingLayerBuilder::new(Url::parse("http://127.0.0.1:9044").unwrap())
+            .marker_field("task")
+            .map_marker_to_index("billing", "billing_logs")
+            .build();
+    tokio::spawn(background_task);
+
+    let result = handle.verify().await;
+
+    assert!(matches!(result, Err(VerifyError::Unreachable { .. })));
+}